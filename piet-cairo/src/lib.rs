@@ -9,13 +9,15 @@ mod text;
 use std::borrow::Cow;
 use std::fmt;
 
-use cairo::{BorrowError, Context, Filter, Format, ImageSurface, Matrix, Status, SurfacePattern};
+use cairo::{
+    BorrowError, Context, Filter, Format, ImageSurface, Matrix, Operator, Status, SurfacePattern,
+};
 
 use piet::kurbo::{Affine, PathEl, Point, QuadBez, Rect, Shape, Size};
 
 use piet::{
-    new_error, Color, Error, ErrorKind, FixedGradient, ImageFormat, InterpolationMode, IntoBrush,
-    LineCap, LineJoin, RenderContext, StrokeStyle,
+    new_error, normalize_stops, Color, Error, ErrorKind, FixedGradient, GradientExtend,
+    ImageFormat, InterpolationMode, IntoBrush, LineCap, LineJoin, RenderContext, StrokeStyle,
 };
 
 pub use crate::text::{
@@ -41,6 +43,32 @@ impl<'a> CairoRenderContext<'a> {
             text: CairoText::new(),
         }
     }
+
+    /// Copy the contents of an area of the surface being drawn to into a new image.
+    ///
+    /// The area is the bounding box of `rect` in device space, rounded out to whole
+    /// pixels, so drawing the image into `rect` reproduces what was drawn there.
+    pub fn capture_image_area(&mut self, rect: impl Into<Rect>) -> Result<ImageSurface, Error> {
+        let device_rect = self
+            .current_transform()
+            .transform_rect_bbox(rect.into())
+            .expand();
+        let image = ImageSurface::create(
+            Format::ARgb32,
+            device_rect.width() as i32,
+            device_rect.height() as i32,
+        )
+        .wrap()?;
+        let ctx = Context::new(&image);
+        ctx.set_source_surface(&self.ctx.get_target(), -device_rect.x0, -device_rect.y0);
+        ctx.set_operator(Operator::Source);
+        ctx.paint();
+        let status = ctx.status();
+        if status != Status::Success {
+            return Err(status).wrap();
+        }
+        Ok(image)
+    }
 }
 
 #[derive(Clone)]
@@ -50,6 +78,16 @@ pub enum Brush {
     Radial(cairo::RadialGradient),
 }
 
+impl fmt::Debug for Brush {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Brush::Solid(rgba) => write!(f, "Brush::Solid(#{:08x})", rgba),
+            Brush::Linear(_) => write!(f, "Brush::Linear"),
+            Brush::Radial(_) => write!(f, "Brush::Radial"),
+        }
+    }
+}
+
 #[derive(Debug)]
 struct WrappedStatus(Status);
 
@@ -140,7 +178,8 @@ impl<'a> RenderContext for CairoRenderContext<'a> {
                 let (x0, y0) = (linear.start.x, linear.start.y);
                 let (x1, y1) = (linear.end.x, linear.end.y);
                 let lg = cairo::LinearGradient::new(x0, y0, x1, y1);
-                set_gradient_stops!(&lg, &linear.stops);
+                set_gradient_stops!(&lg, &normalize_stops(&linear.stops));
+                lg.set_extend(convert_extend(linear.extend));
                 Ok(Brush::Linear(lg))
             }
             FixedGradient::Radial(radial) => {
@@ -148,7 +187,8 @@ impl<'a> RenderContext for CairoRenderContext<'a> {
                 let (xo, yo) = (radial.origin_offset.x, radial.origin_offset.y);
                 let r = radial.radius;
                 let rg = cairo::RadialGradient::new(xc + xo, yc + yo, 0.0, xc, yc, r);
-                set_gradient_stops!(&rg, &radial.stops);
+                set_gradient_stops!(&rg, &normalize_stops(&radial.stops));
+                rg.set_extend(convert_extend(radial.extend));
                 Ok(Brush::Radial(rg))
             }
         }
@@ -251,14 +291,19 @@ impl<'a> RenderContext for CairoRenderContext<'a> {
         buf: &[u8],
         format: ImageFormat,
     ) -> Result<Self::Image, Error> {
-        let cairo_fmt = match format {
-            ImageFormat::Rgb => Format::Rgb24,
-            ImageFormat::RgbaSeparate | ImageFormat::RgbaPremul => Format::ARgb32,
+        let (cairo_fmt, bytes_per_pixel) = match format {
+            ImageFormat::Rgb => (Format::Rgb24, 3),
+            ImageFormat::RgbaSeparate | ImageFormat::RgbaPremul => (Format::ARgb32, 4),
             _ => return Err(new_error(ErrorKind::NotSupported)),
         };
+        let expected_len = width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(bytes_per_pixel));
+        if expected_len != Some(buf.len()) {
+            return Err(new_error(ErrorKind::InvalidInput));
+        }
         let mut image = ImageSurface::create(cairo_fmt, width as i32, height as i32).wrap()?;
         // Confident no borrow errors because we just created it.
-        let bytes_per_pixel = format.bytes_per_pixel();
         let bytes_per_row = width * bytes_per_pixel;
         let stride = image.get_stride() as usize;
         {
@@ -334,6 +379,14 @@ impl<'a> RenderContext for CairoRenderContext<'a> {
     }
 }
 
+fn convert_extend(extend: GradientExtend) -> cairo::Extend {
+    match extend {
+        GradientExtend::Pad => cairo::Extend::Pad,
+        GradientExtend::Repeat => cairo::Extend::Repeat,
+        GradientExtend::Reflect => cairo::Extend::Reflect,
+    }
+}
+
 fn draw_image<'a>(
     ctx: &mut CairoRenderContext<'a>,
     image: &<CairoRenderContext<'a> as RenderContext>::Image,
@@ -414,23 +467,11 @@ impl<'a> CairoRenderContext<'a> {
     fn set_stroke(&mut self, width: f64, style: Option<&StrokeStyle>) {
         self.ctx.set_line_width(width);
 
-        let line_join = style
-            .and_then(|style| style.line_join)
-            .unwrap_or(LineJoin::Miter);
-        self.ctx.set_line_join(convert_line_join(line_join));
-
-        let line_cap = style
-            .and_then(|style| style.line_cap)
-            .unwrap_or(LineCap::Butt);
-        self.ctx.set_line_cap(convert_line_cap(line_cap));
-
-        let miter_limit = style.and_then(|style| style.miter_limit).unwrap_or(10.0);
-        self.ctx.set_miter_limit(miter_limit);
-
-        match style.and_then(|style| style.dash.as_ref()) {
-            None => self.ctx.set_dash(&[], 0.0),
-            Some((dashes, offset)) => self.ctx.set_dash(dashes, *offset),
-        }
+        let style = style.map(StrokeStyle::resolve).unwrap_or_default();
+        self.ctx.set_line_join(convert_line_join(style.line_join));
+        self.ctx.set_line_cap(convert_line_cap(style.line_cap));
+        self.ctx.set_miter_limit(style.miter_limit);
+        self.ctx.set_dash(&style.dashes, style.dash_offset);
     }
 
     fn set_path(&mut self, shape: impl Shape) {
@@ -438,10 +479,17 @@ impl<'a> CairoRenderContext<'a> {
         // state. But just in case, and it should be harmless.
         self.ctx.new_path();
         let mut last = Point::ZERO;
-        for el in shape.to_bez_path(1e-3) {
+        // the start of the current subpath, which is where a close leaves the current point
+        let mut start = Point::ZERO;
+        for (i, el) in shape.to_bez_path(1e-3).enumerate() {
+            if i == 0 && !matches!(el, PathEl::MoveTo(_)) {
+                // a path that doesn't begin with a move starts at the origin
+                self.ctx.move_to(0.0, 0.0);
+            }
             match el {
                 PathEl::MoveTo(p) => {
                     self.ctx.move_to(p.x, p.y);
+                    start = p;
                     last = p;
                 }
                 PathEl::LineTo(p) => {
@@ -459,7 +507,10 @@ impl<'a> CairoRenderContext<'a> {
                     self.ctx.curve_to(p1.x, p1.y, p2.x, p2.y, p3.x, p3.y);
                     last = p3;
                 }
-                PathEl::ClosePath => self.ctx.close_path(),
+                PathEl::ClosePath => {
+                    self.ctx.close_path();
+                    last = start;
+                }
             }
         }
     }
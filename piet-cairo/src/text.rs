@@ -3,6 +3,7 @@
 mod grapheme;
 mod lines;
 
+use std::fmt;
 use std::marker::PhantomData;
 
 use cairo::{FontFace, FontOptions, FontSlant, FontWeight, Matrix, ScaledFont};
@@ -46,6 +47,17 @@ pub struct CairoTextLayout {
 
 pub struct CairoTextLayoutBuilder(CairoTextLayout);
 
+impl fmt::Debug for CairoTextLayout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // the font is a pointer to a cairo object, so it's left out
+        f.debug_struct("CairoTextLayout")
+            .field("text", &self.text)
+            .field("width", &self.width)
+            .field("line_metrics", &self.line_metrics)
+            .finish()
+    }
+}
+
 impl<'a> CairoText<'a> {
     /// Create a new factory that satisfies the piet `Text` trait.
     ///
@@ -206,6 +218,8 @@ impl TextLayout for CairoTextLayout {
 
         let mut htp = hit_test_line_point(&self.font, line, &point);
         htp.metrics.text_position += lm.start_offset;
+        htp.metrics.is_trailing_whitespace =
+            htp.metrics.text_position > lm.end_offset - lm.trailing_whitespace;
 
         if !is_y_inside {
             htp.is_inside = false;
@@ -247,6 +261,8 @@ impl TextLayout for CairoTextLayout {
         if let Some(h) = http.as_mut() {
             h.point.y = y;
             h.metrics.text_position += lm.start_offset;
+            h.metrics.is_trailing_whitespace =
+                h.metrics.text_position > lm.end_offset - lm.trailing_whitespace;
         };
         http
     }
@@ -344,6 +360,7 @@ fn hit_test_line_position(
             },
             metrics: HitTestMetrics {
                 text_position: text_len,
+                is_trailing_whitespace: false,
             },
         });
     }
@@ -360,7 +377,10 @@ fn hit_test_line_position(
 
         Some(HitTestTextPosition {
             point: Point { x: point_x, y: 0.0 },
-            metrics: HitTestMetrics { text_position },
+            metrics: HitTestMetrics {
+                text_position,
+                is_trailing_whitespace: false,
+            },
         })
     } else {
         // iterated to end boundary
@@ -371,6 +391,7 @@ fn hit_test_line_position(
             },
             metrics: HitTestMetrics {
                 text_position: text_len,
+                is_trailing_whitespace: false,
             },
         })
     }
@@ -402,6 +423,18 @@ mod test {
         assert!(x <= max && x >= min);
     }
 
+    #[test]
+    fn test_layout_debug() {
+        let mut text = CairoText::new();
+        let font = text.new_font_by_name("sans-serif", 12.0).build().unwrap();
+        let layout = text
+            .new_text_layout(&font, "piet", f64::INFINITY)
+            .build()
+            .unwrap();
+        let debug = format!("{:?}", layout);
+        assert!(debug.starts_with("CairoTextLayout { text: \"piet\", width: "));
+    }
+
     #[test]
     fn test_hit_test_text_position_basic() {
         let mut text_layout = CairoText::new();
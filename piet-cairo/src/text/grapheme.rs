@@ -107,11 +107,17 @@ mod test {
         };
 
         let expected_curr = Some(HitTestPoint {
-            metrics: HitTestMetrics { text_position: 2 },
+            metrics: HitTestMetrics {
+                text_position: 2,
+                is_trailing_whitespace: false,
+            },
             is_inside: true,
         });
         let expected_next = Some(HitTestPoint {
-            metrics: HitTestMetrics { text_position: 4 },
+            metrics: HitTestMetrics {
+                text_position: 4,
+                is_trailing_whitespace: false,
+            },
             is_inside: true,
         });
 
@@ -12,6 +12,8 @@ use piet_common::Device;
 fn main() {
     #[cfg(not(target_arch = "wasm32"))]
     {
+        // only the Direct2D device needs to be mutable to create targets
+        #[allow(unused_mut)]
         let mut device = Device::new().unwrap();
         let width = 640;
         let height = 480;
@@ -0,0 +1,571 @@
+//! Rendering to a bitmap or an SVG document, chosen at runtime.
+//!
+//! The associated types of [`AnyRenderContext`] are enums over the types of the
+//! Cairo and SVG backends. Resources are tied to the kind of target that created
+//! them: using a brush, image, font or text layout with the other kind of target
+//! does nothing, and is reported as an `InvalidInput` error by
+//! [`status`](#method.status) and [`finish`](#method.finish).
+//!
+//! [`AnyRenderContext`]: struct.AnyRenderContext.html
+
+use std::borrow::Cow;
+
+use piet::kurbo::{Affine, Point, Rect, Shape, Size};
+use piet::{
+    Color, Error, ErrorKind, FixedGradient, Font, FontBuilder, HitTestPoint, HitTestTextPosition,
+    ImageFormat, InterpolationMode, IntoBrush, LineMetric, RenderContext, StrokeStyle, Text,
+    TextLayout, TextLayoutBuilder,
+};
+use piet_cairo::{
+    Brush as CairoBrush, CairoFont, CairoFontBuilder, CairoRenderContext, CairoText,
+    CairoTextLayout, CairoTextLayoutBuilder,
+};
+
+use crate::backend::{BitmapTarget, Device, Image as CairoImage};
+
+type SvgFont = <piet_svg::Text as Text>::Font;
+type SvgFontBuilder = <piet_svg::Text as Text>::FontBuilder;
+type SvgTextLayoutBuilder = <piet_svg::Text as Text>::TextLayoutBuilder;
+
+/// A device creating either bitmap or SVG targets.
+pub enum AnyDevice {
+    Bitmap(Device),
+    Svg,
+}
+
+/// A bitmap or an SVG document, providing an [`AnyRenderContext`].
+///
+/// [`AnyRenderContext`]: struct.AnyRenderContext.html
+pub enum AnyTarget<'a> {
+    Bitmap(BitmapTarget<'a>),
+    Svg(Box<piet_svg::RenderContext>),
+}
+
+/// A `RenderContext` drawing to either a bitmap or an SVG document.
+pub struct AnyRenderContext<'a> {
+    inner: AnyInner<'a>,
+    text: AnyText<'a>,
+    err: Result<(), Error>,
+}
+
+enum AnyInner<'a> {
+    Bitmap(CairoRenderContext<'a>),
+    Svg(&'a mut piet_svg::RenderContext),
+}
+
+#[derive(Clone)]
+pub enum AnyBrush {
+    Bitmap(CairoBrush),
+    Svg(piet_svg::Brush),
+}
+
+pub enum AnyImage {
+    Bitmap(CairoImage),
+    Svg(piet_svg::Image),
+}
+
+pub enum AnyText<'a> {
+    Bitmap(CairoText<'a>),
+    Svg(piet_svg::Text),
+}
+
+pub enum AnyFont {
+    Bitmap(CairoFont),
+    Svg(SvgFont),
+}
+
+pub enum AnyFontBuilder {
+    Bitmap(CairoFontBuilder),
+    Svg(SvgFontBuilder),
+}
+
+#[derive(Clone)]
+pub enum AnyTextLayout {
+    Bitmap(CairoTextLayout),
+    Svg(piet_svg::TextLayout),
+}
+
+pub enum AnyTextLayoutBuilder {
+    Bitmap(CairoTextLayoutBuilder),
+    Svg(SvgTextLayoutBuilder),
+    /// The font was created for the other kind of target.
+    Mismatch,
+}
+
+fn mismatch() -> Error {
+    piet::new_error(ErrorKind::InvalidInput)
+}
+
+impl AnyDevice {
+    /// Create a new target.
+    ///
+    /// For an SVG document, `width` and `height` set the document size, and
+    /// `pix_scale` is ignored.
+    pub fn target(
+        &self,
+        width: usize,
+        height: usize,
+        pix_scale: f64,
+    ) -> Result<AnyTarget<'_>, Error> {
+        match self {
+            AnyDevice::Bitmap(device) => Ok(AnyTarget::Bitmap(
+                device.bitmap_target(width, height, pix_scale)?,
+            )),
+            AnyDevice::Svg => Ok(AnyTarget::Svg(Box::new(
+                piet_svg::RenderContext::with_size(Size::new(width as f64, height as f64)),
+            ))),
+        }
+    }
+}
+
+impl<'a> AnyTarget<'a> {
+    /// Get a piet `RenderContext` for the target.
+    ///
+    /// Note: caller is responsible for calling `finish` on the render
+    /// context at the end of rendering.
+    pub fn render_context(&mut self) -> AnyRenderContext<'_> {
+        let (inner, text) = match self {
+            AnyTarget::Bitmap(target) => (
+                AnyInner::Bitmap(target.render_context()),
+                AnyText::Bitmap(CairoText::new()),
+            ),
+            AnyTarget::Svg(ctx) => {
+                // the document's own text factory, which is given back when the
+                // `AnyRenderContext` is dropped
+                let text = std::mem::replace(ctx.text(), piet_svg::Text::new());
+                (AnyInner::Svg(ctx), AnyText::Svg(text))
+            }
+        };
+        AnyRenderContext {
+            inner,
+            text,
+            err: Ok(()),
+        }
+    }
+}
+
+impl Drop for AnyRenderContext<'_> {
+    fn drop(&mut self) {
+        if let (AnyInner::Svg(ctx), AnyText::Svg(text)) = (&mut self.inner, &mut self.text) {
+            std::mem::swap(ctx.text(), text);
+        }
+    }
+}
+
+impl<'a> RenderContext for AnyRenderContext<'a> {
+    type Brush = AnyBrush;
+    type Image = AnyImage;
+    type Text = AnyText<'a>;
+    type TextLayout = AnyTextLayout;
+
+    fn status(&mut self) -> Result<(), Error> {
+        std::mem::replace(&mut self.err, Ok(()))?;
+        match &mut self.inner {
+            AnyInner::Bitmap(ctx) => ctx.status(),
+            AnyInner::Svg(ctx) => ctx.status(),
+        }
+    }
+
+    fn solid_brush(&mut self, color: Color) -> AnyBrush {
+        match &mut self.inner {
+            AnyInner::Bitmap(ctx) => AnyBrush::Bitmap(ctx.solid_brush(color)),
+            AnyInner::Svg(ctx) => AnyBrush::Svg(ctx.solid_brush(color)),
+        }
+    }
+
+    fn gradient(&mut self, gradient: impl Into<FixedGradient>) -> Result<AnyBrush, Error> {
+        match &mut self.inner {
+            AnyInner::Bitmap(ctx) => Ok(AnyBrush::Bitmap(ctx.gradient(gradient)?)),
+            AnyInner::Svg(ctx) => Ok(AnyBrush::Svg(ctx.gradient(gradient)?)),
+        }
+    }
+
+    fn clear(&mut self, color: Color) {
+        match &mut self.inner {
+            AnyInner::Bitmap(ctx) => ctx.clear(color),
+            AnyInner::Svg(ctx) => ctx.clear(color),
+        }
+    }
+
+    fn stroke(&mut self, shape: impl Shape, brush: &impl IntoBrush<Self>, width: f64) {
+        let brush = brush.make_brush(self, || shape.bounding_box());
+        match (&mut self.inner, &*brush) {
+            (AnyInner::Bitmap(ctx), AnyBrush::Bitmap(brush)) => ctx.stroke(shape, brush, width),
+            (AnyInner::Svg(ctx), AnyBrush::Svg(brush)) => ctx.stroke(shape, brush, width),
+            _ => self.err = Err(mismatch()),
+        }
+    }
+
+    fn stroke_styled(
+        &mut self,
+        shape: impl Shape,
+        brush: &impl IntoBrush<Self>,
+        width: f64,
+        style: &StrokeStyle,
+    ) {
+        let brush = brush.make_brush(self, || shape.bounding_box());
+        match (&mut self.inner, &*brush) {
+            (AnyInner::Bitmap(ctx), AnyBrush::Bitmap(brush)) => {
+                ctx.stroke_styled(shape, brush, width, style)
+            }
+            (AnyInner::Svg(ctx), AnyBrush::Svg(brush)) => {
+                ctx.stroke_styled(shape, brush, width, style)
+            }
+            _ => self.err = Err(mismatch()),
+        }
+    }
+
+    fn fill(&mut self, shape: impl Shape, brush: &impl IntoBrush<Self>) {
+        let brush = brush.make_brush(self, || shape.bounding_box());
+        match (&mut self.inner, &*brush) {
+            (AnyInner::Bitmap(ctx), AnyBrush::Bitmap(brush)) => ctx.fill(shape, brush),
+            (AnyInner::Svg(ctx), AnyBrush::Svg(brush)) => ctx.fill(shape, brush),
+            _ => self.err = Err(mismatch()),
+        }
+    }
+
+    fn fill_even_odd(&mut self, shape: impl Shape, brush: &impl IntoBrush<Self>) {
+        let brush = brush.make_brush(self, || shape.bounding_box());
+        match (&mut self.inner, &*brush) {
+            (AnyInner::Bitmap(ctx), AnyBrush::Bitmap(brush)) => ctx.fill_even_odd(shape, brush),
+            (AnyInner::Svg(ctx), AnyBrush::Svg(brush)) => ctx.fill_even_odd(shape, brush),
+            _ => self.err = Err(mismatch()),
+        }
+    }
+
+    fn clip(&mut self, shape: impl Shape) {
+        match &mut self.inner {
+            AnyInner::Bitmap(ctx) => ctx.clip(shape),
+            AnyInner::Svg(ctx) => ctx.clip(shape),
+        }
+    }
+
+    fn text(&mut self) -> &mut AnyText<'a> {
+        &mut self.text
+    }
+
+    fn draw_text(
+        &mut self,
+        layout: &AnyTextLayout,
+        pos: impl Into<Point>,
+        brush: &impl IntoBrush<Self>,
+    ) {
+        let pos = pos.into();
+        let brush = brush.make_brush(self, || {
+            let height = (0..layout.line_count())
+                .filter_map(|line| layout.line_metric(line))
+                .map(|lm| lm.height)
+                .sum::<f64>();
+            Rect::from_origin_size(pos, (layout.width(), height))
+        });
+        match (&mut self.inner, layout, &*brush) {
+            (AnyInner::Bitmap(ctx), AnyTextLayout::Bitmap(layout), AnyBrush::Bitmap(brush)) => {
+                ctx.draw_text(layout, pos, brush)
+            }
+            (AnyInner::Svg(ctx), AnyTextLayout::Svg(layout), AnyBrush::Svg(brush)) => {
+                ctx.draw_text(layout, pos, brush)
+            }
+            _ => self.err = Err(mismatch()),
+        }
+    }
+
+    fn save(&mut self) -> Result<(), Error> {
+        match &mut self.inner {
+            AnyInner::Bitmap(ctx) => ctx.save(),
+            AnyInner::Svg(ctx) => ctx.save(),
+        }
+    }
+
+    fn restore(&mut self) -> Result<(), Error> {
+        match &mut self.inner {
+            AnyInner::Bitmap(ctx) => ctx.restore(),
+            AnyInner::Svg(ctx) => ctx.restore(),
+        }
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        std::mem::replace(&mut self.err, Ok(()))?;
+        match &mut self.inner {
+            AnyInner::Bitmap(ctx) => ctx.finish(),
+            AnyInner::Svg(ctx) => ctx.finish(),
+        }
+    }
+
+    fn transform(&mut self, transform: Affine) {
+        match &mut self.inner {
+            AnyInner::Bitmap(ctx) => ctx.transform(transform),
+            AnyInner::Svg(ctx) => ctx.transform(transform),
+        }
+    }
+
+    fn make_image(
+        &mut self,
+        width: usize,
+        height: usize,
+        buf: &[u8],
+        format: ImageFormat,
+    ) -> Result<AnyImage, Error> {
+        match &mut self.inner {
+            AnyInner::Bitmap(ctx) => Ok(AnyImage::Bitmap(
+                ctx.make_image(width, height, buf, format)?,
+            )),
+            AnyInner::Svg(ctx) => Ok(AnyImage::Svg(ctx.make_image(width, height, buf, format)?)),
+        }
+    }
+
+    fn draw_image(
+        &mut self,
+        image: &AnyImage,
+        dst_rect: impl Into<Rect>,
+        interp: InterpolationMode,
+    ) {
+        match (&mut self.inner, image) {
+            (AnyInner::Bitmap(ctx), AnyImage::Bitmap(image)) => {
+                ctx.draw_image(image, dst_rect, interp)
+            }
+            (AnyInner::Svg(ctx), AnyImage::Svg(image)) => ctx.draw_image(image, dst_rect, interp),
+            _ => self.err = Err(mismatch()),
+        }
+    }
+
+    fn draw_image_area(
+        &mut self,
+        image: &AnyImage,
+        src_rect: impl Into<Rect>,
+        dst_rect: impl Into<Rect>,
+        interp: InterpolationMode,
+    ) {
+        match (&mut self.inner, image) {
+            (AnyInner::Bitmap(ctx), AnyImage::Bitmap(image)) => {
+                ctx.draw_image_area(image, src_rect, dst_rect, interp)
+            }
+            (AnyInner::Svg(ctx), AnyImage::Svg(image)) => {
+                ctx.draw_image_area(image, src_rect, dst_rect, interp)
+            }
+            _ => self.err = Err(mismatch()),
+        }
+    }
+
+    fn blurred_rect(&mut self, rect: Rect, blur_radius: f64, brush: &impl IntoBrush<Self>) {
+        let brush = brush.make_brush(self, || rect);
+        match (&mut self.inner, &*brush) {
+            (AnyInner::Bitmap(ctx), AnyBrush::Bitmap(brush)) => {
+                ctx.blurred_rect(rect, blur_radius, brush)
+            }
+            (AnyInner::Svg(ctx), AnyBrush::Svg(brush)) => {
+                ctx.blurred_rect(rect, blur_radius, brush)
+            }
+            _ => self.err = Err(mismatch()),
+        }
+    }
+
+    fn current_transform(&self) -> Affine {
+        match &self.inner {
+            AnyInner::Bitmap(ctx) => ctx.current_transform(),
+            AnyInner::Svg(ctx) => ctx.current_transform(),
+        }
+    }
+}
+
+impl<'a> IntoBrush<AnyRenderContext<'a>> for AnyBrush {
+    fn make_brush<'b>(
+        &'b self,
+        _piet: &mut AnyRenderContext<'a>,
+        _bbox: impl FnOnce() -> Rect,
+    ) -> Cow<'b, AnyBrush> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl<'a> Text for AnyText<'a> {
+    type Font = AnyFont;
+    type FontBuilder = AnyFontBuilder;
+    type TextLayout = AnyTextLayout;
+    type TextLayoutBuilder = AnyTextLayoutBuilder;
+
+    fn new_font_by_name(&mut self, name: &str, size: f64) -> AnyFontBuilder {
+        match self {
+            AnyText::Bitmap(text) => AnyFontBuilder::Bitmap(text.new_font_by_name(name, size)),
+            AnyText::Svg(text) => AnyFontBuilder::Svg(text.new_font_by_name(name, size)),
+        }
+    }
+
+    fn new_text_layout(
+        &mut self,
+        font: &AnyFont,
+        text: &str,
+        width: impl Into<Option<f64>>,
+    ) -> AnyTextLayoutBuilder {
+        match (self, font) {
+            (AnyText::Bitmap(factory), AnyFont::Bitmap(font)) => {
+                AnyTextLayoutBuilder::Bitmap(factory.new_text_layout(font, text, width))
+            }
+            (AnyText::Svg(factory), AnyFont::Svg(font)) => {
+                AnyTextLayoutBuilder::Svg(factory.new_text_layout(font, text, width))
+            }
+            _ => AnyTextLayoutBuilder::Mismatch,
+        }
+    }
+}
+
+impl Font for AnyFont {}
+
+impl FontBuilder for AnyFontBuilder {
+    type Out = AnyFont;
+
+    fn build(self) -> Result<AnyFont, Error> {
+        match self {
+            AnyFontBuilder::Bitmap(builder) => Ok(AnyFont::Bitmap(builder.build()?)),
+            AnyFontBuilder::Svg(builder) => Ok(AnyFont::Svg(builder.build()?)),
+        }
+    }
+}
+
+impl TextLayoutBuilder for AnyTextLayoutBuilder {
+    type Out = AnyTextLayout;
+
+    fn build(self) -> Result<AnyTextLayout, Error> {
+        match self {
+            AnyTextLayoutBuilder::Bitmap(builder) => Ok(AnyTextLayout::Bitmap(builder.build()?)),
+            AnyTextLayoutBuilder::Svg(builder) => Ok(AnyTextLayout::Svg(builder.build()?)),
+            AnyTextLayoutBuilder::Mismatch => Err(mismatch()),
+        }
+    }
+}
+
+impl TextLayout for AnyTextLayout {
+    fn width(&self) -> f64 {
+        match self {
+            AnyTextLayout::Bitmap(layout) => layout.width(),
+            AnyTextLayout::Svg(layout) => layout.width(),
+        }
+    }
+
+    fn update_width(&mut self, new_width: impl Into<Option<f64>>) -> Result<(), Error> {
+        match self {
+            AnyTextLayout::Bitmap(layout) => layout.update_width(new_width),
+            AnyTextLayout::Svg(layout) => layout.update_width(new_width),
+        }
+    }
+
+    fn line_text(&self, line_number: usize) -> Option<&str> {
+        match self {
+            AnyTextLayout::Bitmap(layout) => layout.line_text(line_number),
+            AnyTextLayout::Svg(layout) => layout.line_text(line_number),
+        }
+    }
+
+    fn line_metric(&self, line_number: usize) -> Option<LineMetric> {
+        match self {
+            AnyTextLayout::Bitmap(layout) => layout.line_metric(line_number),
+            AnyTextLayout::Svg(layout) => layout.line_metric(line_number),
+        }
+    }
+
+    fn line_count(&self) -> usize {
+        match self {
+            AnyTextLayout::Bitmap(layout) => layout.line_count(),
+            AnyTextLayout::Svg(layout) => layout.line_count(),
+        }
+    }
+
+    fn hit_test_point(&self, point: Point) -> HitTestPoint {
+        match self {
+            AnyTextLayout::Bitmap(layout) => layout.hit_test_point(point),
+            AnyTextLayout::Svg(layout) => layout.hit_test_point(point),
+        }
+    }
+
+    fn hit_test_text_position(&self, text_position: usize) -> Option<HitTestTextPosition> {
+        match self {
+            AnyTextLayout::Bitmap(layout) => layout.hit_test_text_position(text_position),
+            AnyTextLayout::Svg(layout) => layout.hit_test_text_position(text_position),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draw(ctx: &mut impl RenderContext) {
+        ctx.fill(Rect::new(2.0, 2.0, 14.0, 14.0), &Color::rgb8(0xff, 0, 0));
+        let font = ctx
+            .text()
+            .new_font_by_name("sans-serif", 12.0)
+            .build()
+            .unwrap();
+        let layout = ctx
+            .text()
+            .new_text_layout(&font, "piet", None)
+            .build()
+            .unwrap();
+        ctx.draw_text(&layout, (0.0, 12.0), &Color::BLACK);
+        ctx.finish().unwrap();
+    }
+
+    #[test]
+    fn bitmap_and_svg() {
+        for device in &[AnyDevice::Bitmap(Device::new().unwrap()), AnyDevice::Svg] {
+            let mut target = device.target(16, 16, 1.0).unwrap();
+            draw(&mut target.render_context());
+            match target {
+                AnyTarget::Bitmap(mut target) => {
+                    let pixels = target.get_raw_pixels(ImageFormat::RgbaPremul).unwrap();
+                    assert!(pixels.iter().any(|&b| b != 0));
+                }
+                AnyTarget::Svg(ctx) => {
+                    let svg = ctx.to_string();
+                    assert!(svg.contains("<rect"));
+                    assert!(svg.contains("piet"));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn svg_text_factory() {
+        let mut target = AnyDevice::Svg.target(16, 16, 1.0).unwrap();
+        if let AnyTarget::Svg(ctx) = &mut target {
+            ctx.set_embed_fonts(true);
+            ctx.text()
+                .load_font_data("Embedded", &b"OTTO font data"[..]);
+        }
+        {
+            let mut ctx = target.render_context();
+            let font = ctx
+                .text()
+                .new_font_by_name("Embedded", 10.0)
+                .build()
+                .unwrap();
+            let layout = ctx
+                .text()
+                .new_text_layout(&font, "hi", None)
+                .build()
+                .unwrap();
+            ctx.draw_text(&layout, (0.0, 10.0), &Color::BLACK);
+            ctx.finish().unwrap();
+        }
+        match target {
+            AnyTarget::Svg(mut ctx) => {
+                assert!(ctx.to_string().contains("@font-face"));
+                // the factory is given back, with the font data still loaded
+                assert!(format!("{:?}", ctx.text()).contains("Embedded"));
+            }
+            AnyTarget::Bitmap(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn mismatched_brush() {
+        let device = AnyDevice::Bitmap(Device::new().unwrap());
+        let mut bitmap = device.target(4, 4, 1.0).unwrap();
+        let mut svg = AnyDevice::Svg.target(4, 4, 1.0).unwrap();
+        let brush = svg.render_context().solid_brush(Color::WHITE);
+
+        let mut ctx = bitmap.render_context();
+        ctx.fill(Rect::new(0.0, 0.0, 4.0, 4.0), &brush);
+        assert!(ctx.status().is_err());
+        assert!(ctx.finish().is_ok());
+    }
+}
@@ -3,16 +3,21 @@
 
 //! Support for piet Cairo back-end.
 
-use cairo::{Context, Format, ImageSurface};
+#[cfg(feature = "pdf")]
+use cairo::PdfSurface;
+use cairo::{Context, ImageSurface, Operator, Status};
 #[cfg(feature = "png")]
-use png::{ColorType, Encoder};
+use png::{BitDepth, ColorType, Encoder};
+use std::fmt;
 #[cfg(feature = "png")]
 use std::fs::File;
 #[cfg(feature = "png")]
-use std::io::BufWriter;
+use std::io::{BufWriter, Write};
 use std::marker::PhantomData;
 use std::path::Path;
 
+use crate::image_util;
+use piet::kurbo::Rect;
 use piet::{ErrorKind, ImageFormat};
 #[doc(hidden)]
 pub use piet_cairo::*;
@@ -55,16 +60,45 @@ pub type PietTextLayoutBuilder<'a> = CairoTextLayoutBuilder;
 /// This type matches `RenderContext::Image`
 pub type Image = ImageSurface;
 
+/// The pixel format of a bitmap target's surface.
+pub use cairo::Format;
+
 /// A struct that can be used to create bitmap render contexts.
 ///
-/// In the case of Cairo, being a software renderer, no state is needed.
+/// In the case of Cairo, being a software renderer, no state is needed, so a
+/// device can be shared between threads to create targets concurrently.
 pub struct Device;
 
 /// A struct provides a `RenderContext` and then can have its bitmap extracted.
 pub struct BitmapTarget<'a> {
     surface: ImageSurface,
+    /// The context drawing to `surface`, which is dropped while the pixels are read, as
+    /// the surface data can only be borrowed while nothing else references it.
+    cr: Option<Context>,
+    pix_scale: f64,
+    phantom: PhantomData<&'a mut [u8]>,
+}
+
+/// A struct provides a `RenderContext` drawing to a PDF file.
+#[cfg(feature = "pdf")]
+pub struct PdfTarget {
+    surface: PdfSurface,
     cr: Context,
-    phantom: PhantomData<&'a ()>,
+}
+
+/// A caller's pixel buffer, handed to cairo as surface data.
+///
+/// The buffer is borrowed for the lifetime of the `BitmapTarget` owning the surface,
+/// which finishes the surface when dropped so that cairo no longer touches it.
+struct BorrowedData {
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl AsMut<[u8]> for BorrowedData {
+    fn as_mut(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
 }
 
 impl Device {
@@ -74,22 +108,139 @@ impl Device {
     }
 
     /// Create a new bitmap target.
+    ///
+    /// `width` and `height` are in logical units; the bitmap itself is `pix_scale`
+    /// times larger in each dimension, rounded up.
     pub fn bitmap_target(
-        &mut self,
+        &self,
         width: usize,
         height: usize,
         pix_scale: f64,
     ) -> Result<BitmapTarget, piet::Error> {
-        let surface = ImageSurface::create(Format::ARgb32, width as i32, height as i32).unwrap();
-        let cr = Context::new(&surface);
-        cr.scale(pix_scale, pix_scale);
+        self.bitmap_target_with_format(width, height, pix_scale, Format::ARgb32)
+    }
+
+    /// Create a new bitmap target with the given surface format.
+    ///
+    /// `Format::ARgb32`, `Format::Rgb24` and `Format::A8` surfaces can be read back
+    /// with [`BitmapTarget::get_raw_pixels`].
+    ///
+    /// [`BitmapTarget::get_raw_pixels`]: struct.BitmapTarget.html#method.get_raw_pixels
+    pub fn bitmap_target_with_format(
+        &self,
+        width: usize,
+        height: usize,
+        pix_scale: f64,
+        format: Format,
+    ) -> Result<BitmapTarget<'_>, piet::Error> {
+        let width = (width as f64 * pix_scale).ceil() as i32;
+        let height = (height as f64 * pix_scale).ceil() as i32;
+        let surface = ImageSurface::create(format, width, height).map_err(status_error)?;
         let phantom = Default::default();
         Ok(BitmapTarget {
             surface,
-            cr,
+            cr: None,
+            pix_scale,
             phantom,
         })
     }
+
+    /// Create a new bitmap target that renders directly into `data`.
+    ///
+    /// The surface has `Format::ARgb32`: each pixel is a native-endian `u32` holding
+    /// premultiplied alpha in the upper 8 bits, followed by red, green and blue. On
+    /// little-endian machines, the bytes of a pixel are thus stored as B, G, R, A.
+    /// Rows start every `stride` bytes, which must be a multiple of 4 and at least
+    /// `width * 4`, and `data` must hold at least `height * stride` bytes.
+    pub fn bitmap_target_for_data<'a>(
+        &self,
+        width: usize,
+        height: usize,
+        stride: usize,
+        data: &'a mut [u8],
+    ) -> Result<BitmapTarget<'a>, piet::Error> {
+        let fits_i32 = |n: usize| n <= i32::MAX as usize;
+        let big_enough = match (width.checked_mul(4), height.checked_mul(stride)) {
+            (Some(min_stride), Some(min_len)) => stride >= min_stride && data.len() >= min_len,
+            _ => false,
+        };
+        if !(big_enough && fits_i32(width) && fits_i32(height) && fits_i32(stride)) {
+            return Err(piet::new_error(ErrorKind::InvalidInput));
+        }
+        let data = BorrowedData {
+            ptr: data.as_mut_ptr(),
+            len: data.len(),
+        };
+        let surface = ImageSurface::create_for_data(
+            data,
+            Format::ARgb32,
+            width as i32,
+            height as i32,
+            stride as i32,
+        )
+        .map_err(status_error)?;
+        let phantom = Default::default();
+        Ok(BitmapTarget {
+            surface,
+            cr: None,
+            pix_scale: 1.0,
+            phantom,
+        })
+    }
+
+    /// Create a new PDF target, writing a single page to the file at `path`.
+    ///
+    /// `width` and `height` are the size of the page, in points.
+    #[cfg(feature = "pdf")]
+    pub fn pdf_target<P: AsRef<Path>>(
+        &self,
+        width: f64,
+        height: f64,
+        path: P,
+    ) -> Result<PdfTarget, piet::Error> {
+        let surface = PdfSurface::new(width, height, path).map_err(status_error)?;
+        let cr = Context::new(&surface);
+        Ok(PdfTarget { surface, cr })
+    }
+}
+
+#[cfg(feature = "pdf")]
+impl PdfTarget {
+    /// Get a piet `RenderContext` for the document.
+    ///
+    /// Note: caller is responsible for calling `finish` on the render
+    /// context at the end of rendering.
+    pub fn render_context(&mut self) -> CairoRenderContext<'_> {
+        CairoRenderContext::new(&mut self.cr)
+    }
+
+    /// Finish the document, writing out the rest of the file.
+    pub fn finish(self) -> Result<(), piet::Error> {
+        let PdfTarget { surface, cr } = self;
+        drop(cr);
+        surface.finish();
+        match surface.status() {
+            Status::Success => Ok(()),
+            status => Err(status_error(status)),
+        }
+    }
+}
+
+/// A cairo error status, as the source of a piet `BackendError`.
+#[derive(Debug)]
+struct CairoError(Status);
+
+impl fmt::Display for CairoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Cairo error: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for CairoError {}
+
+fn status_error(status: Status) -> piet::Error {
+    let e: Box<dyn std::error::Error> = Box::new(CairoError(status));
+    e.into()
 }
 
 impl<'a> BitmapTarget<'a> {
@@ -98,7 +249,40 @@ impl<'a> BitmapTarget<'a> {
     /// Note: caller is responsible for calling `finish` on the render
     /// context at the end of rendering.
     pub fn render_context(&mut self) -> CairoRenderContext {
-        CairoRenderContext::new(&mut self.cr)
+        let surface = &self.surface;
+        let pix_scale = self.pix_scale;
+        let cr = self.cr.get_or_insert_with(|| {
+            let cr = Context::new(surface);
+            cr.scale(pix_scale, pix_scale);
+            cr
+        });
+        CairoRenderContext::new(cr)
+    }
+
+    /// Clear the whole bitmap to transparent.
+    ///
+    /// Unlike `clear` on the render context, this ignores any clip or transform left
+    /// in place by previous rendering, which is useful when reusing a target for
+    /// successive frames.
+    pub fn clear_all(&mut self) {
+        let cr = Context::new(&self.surface);
+        cr.set_operator(Operator::Clear);
+        cr.paint();
+    }
+
+    /// The width of the bitmap, in physical pixels.
+    pub fn width(&self) -> usize {
+        self.surface.get_width() as usize
+    }
+
+    /// The height of the bitmap, in physical pixels.
+    pub fn height(&self) -> usize {
+        self.surface.get_height() as usize
+    }
+
+    /// The number of bytes between the starts of consecutive rows of the surface.
+    pub fn stride(&self) -> usize {
+        self.surface.get_stride() as usize
     }
 
     /// Get raw RGBA pixels from the bitmap.
@@ -106,59 +290,134 @@ impl<'a> BitmapTarget<'a> {
         self.get_raw_pixels(fmt)
     }
 
-    /// Get raw RGBA pixels without consuming the bitmap.
+    /// Get raw pixels without consuming the bitmap.
+    ///
+    /// For `ImageFormat::Rgb`, the pixels are composited over black, and for
+    /// `ImageFormat::Grayscale`, alpha is discarded.
+    ///
+    /// An `Rgb24` surface reads back as opaque. An `A8` surface reads back as black
+    /// with its alpha, except for `ImageFormat::Grayscale`, which yields the alpha
+    /// values themselves.
     pub fn get_raw_pixels(&mut self, fmt: ImageFormat) -> Result<Vec<u8>, piet::Error> {
-        // TODO: convert other formats.
-        if fmt != ImageFormat::RgbaPremul {
-            return Err(piet::new_error(ErrorKind::NotSupported));
-        }
-
-        // A bad hack; because we take self by reference we can't move cr (even
-        // temporarily) so instead we put in a fake cr while we're extracting
-        // data from the surface.
-        //
-        // I don't know of a better way to create a `Context` that we'll never
-        // use; if the fields on `Context` were public we could just construct
-        // one with a null pointer.
-        let surface = ImageSurface::create(Format::ARgb32, 0, 0).unwrap();
-        let temp = Context::new(&surface);
-        drop(std::mem::replace(&mut self.cr, temp));
+        let rect = Rect::new(0.0, 0.0, self.width() as f64, self.height() as f64);
+        self.get_raw_pixels_area(rect, fmt)
+    }
+
+    /// Get raw pixels for an area of the bitmap, without consuming it.
+    ///
+    /// `rect` is in physical pixels, and is expanded to whole pixels and clamped to
+    /// the bounds of the bitmap. The rows of the returned pixels are tightly packed.
+    /// Formats are handled as in [`get_raw_pixels`].
+    ///
+    /// [`get_raw_pixels`]: #method.get_raw_pixels
+    pub fn get_raw_pixels_area(
+        &mut self,
+        rect: Rect,
+        fmt: ImageFormat,
+    ) -> Result<Vec<u8>, piet::Error> {
+        match fmt {
+            ImageFormat::Rgb
+            | ImageFormat::RgbaSeparate
+            | ImageFormat::RgbaPremul
+            | ImageFormat::Grayscale => {}
+            _ => return Err(piet::new_error(ErrorKind::NotSupported)),
+        }
+        let format = self.surface.get_format();
+        match format {
+            Format::ARgb32 | Format::Rgb24 | Format::A8 => {}
+            _ => return Err(piet::new_error(ErrorKind::NotSupported)),
+        }
+
+        // a new context is created the next time one is needed
+        self.cr = None;
 
         self.surface.flush();
-        let stride = self.surface.get_stride() as usize;
-        let width = self.surface.get_width() as usize;
-        let height = self.surface.get_height() as usize;
+        let stride = self.stride();
+        let bounds = Rect::new(0.0, 0.0, self.width() as f64, self.height() as f64);
+        let rect = rect.abs().expand().intersect(bounds);
+        let (x0, y0) = (rect.x0 as usize, rect.y0 as usize);
+        let width = rect.width() as usize;
+        let height = rect.height() as usize;
+        let bytes_per_pixel = if format == Format::A8 { 1 } else { 4 };
         let mut raw_data = vec![0; width * height * 4];
         let buf = self
             .surface
             .get_data()
             .map_err(Into::<Box<dyn std::error::Error>>::into)?;
         for y in 0..height {
-            let src_off = y * stride;
+            let src_off = (y0 + y) * stride + x0 * bytes_per_pixel;
             let dst_off = y * width * 4;
             for x in 0..width {
+                if format == Format::A8 {
+                    raw_data[dst_off + x * 4 + 3] = buf[src_off + x];
+                    continue;
+                }
                 raw_data[dst_off + x * 4 + 0] = buf[src_off + x * 4 + 2];
                 raw_data[dst_off + x * 4 + 1] = buf[src_off + x * 4 + 1];
                 raw_data[dst_off + x * 4 + 2] = buf[src_off + x * 4 + 0];
-                raw_data[dst_off + x * 4 + 3] = buf[src_off + x * 4 + 3];
+                // the high byte of an Rgb24 pixel is unused
+                raw_data[dst_off + x * 4 + 3] = if format == Format::Rgb24 {
+                    0xff
+                } else {
+                    buf[src_off + x * 4 + 3]
+                };
             }
         }
-
         drop(buf);
-        self.cr = Context::new(&self.surface);
 
-        Ok(raw_data)
+        if fmt == ImageFormat::Grayscale && format == Format::A8 {
+            return Ok(raw_data.chunks(4).map(|pixel| pixel[3]).collect());
+        }
+        image_util::convert(
+            &raw_data,
+            ImageFormat::RgbaPremul,
+            fmt,
+            width,
+            height,
+            width * 4,
+        )
     }
 
     /// Save bitmap to RGBA PNG file
     #[cfg(feature = "png")]
     pub fn save_to_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), piet::Error> {
-        let height = self.surface.get_height();
-        let width = self.surface.get_width();
-        let image = self.get_raw_pixels(ImageFormat::RgbaPremul)?;
         let file = BufWriter::new(File::create(path).map_err(|e| Into::<Box<_>>::into(e))?);
-        let mut encoder = Encoder::new(file, width as u32, height as u32);
+        self.save_to_writer(file)
+    }
+
+    /// Save bitmap to RGBA PNG file with 16 bits per channel
+    ///
+    /// The bitmap only has 8 bits per channel, which are widened to 16 bits.
+    #[cfg(feature = "png")]
+    pub fn save_to_file_16bit<P: AsRef<Path>>(&mut self, path: P) -> Result<(), piet::Error> {
+        let file = File::create(path).map_err(Into::<Box<dyn std::error::Error>>::into)?;
+        self.write_png(BufWriter::new(file), BitDepth::Sixteen)
+    }
+
+    /// Encode bitmap as an RGBA PNG into a writer
+    #[cfg(feature = "png")]
+    pub fn save_to_writer<W: Write>(&mut self, writer: W) -> Result<(), piet::Error> {
+        self.write_png(writer, BitDepth::Eight)
+    }
+
+    #[cfg(feature = "png")]
+    fn write_png<W: Write>(&mut self, writer: W, depth: BitDepth) -> Result<(), piet::Error> {
+        let height = self.height();
+        let width = self.width();
+        // PNG stores straight alpha
+        let mut image = self.get_raw_pixels(ImageFormat::RgbaSeparate)?;
+        if depth == BitDepth::Sixteen {
+            // 16-bit samples are big-endian, and byte * 257 maps 0xff to 0xffff
+            let mut wide = Vec::with_capacity(image.len() * 2);
+            for b in image {
+                wide.push(b);
+                wide.push(b);
+            }
+            image = wide;
+        }
+        let mut encoder = Encoder::new(writer, width as u32, height as u32);
         encoder.set_color(ColorType::RGBA);
+        encoder.set_depth(depth);
         encoder
             .write_header()
             .map_err(|e| Into::<Box<_>>::into(e))?
@@ -172,4 +431,488 @@ impl<'a> BitmapTarget<'a> {
     pub fn save_to_file<P: AsRef<Path>>(&mut self, _path: P) -> Result<(), piet::Error> {
         Err(piet::new_error(ErrorKind::MissingFeature))
     }
+
+    /// Stub for feature is missing
+    #[cfg(not(feature = "png"))]
+    pub fn save_to_file_16bit<P: AsRef<Path>>(&mut self, _path: P) -> Result<(), piet::Error> {
+        Err(piet::new_error(ErrorKind::MissingFeature))
+    }
+
+    /// Stub for feature is missing
+    #[cfg(not(feature = "png"))]
+    pub fn save_to_writer<W: std::io::Write>(&mut self, _writer: W) -> Result<(), piet::Error> {
+        Err(piet::new_error(ErrorKind::MissingFeature))
+    }
+}
+
+impl<'a> Drop for BitmapTarget<'a> {
+    fn drop(&mut self) {
+        // the surface data may be borrowed, so make sure rendering is done with it
+        self.cr = None;
+        self.surface.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use piet::kurbo::Rect;
+    use piet::{Color, RenderContext};
+
+    #[test]
+    fn fill_with_color() {
+        let device = Device::new().unwrap();
+        let mut target = device.bitmap_target(4, 4, 1.0).unwrap();
+        {
+            let mut piet = target.render_context();
+            piet.fill(Rect::new(0.0, 0.0, 4.0, 4.0), &Color::rgb8(0xff, 0, 0));
+            piet.finish().unwrap();
+        }
+        let pixels = target.into_raw_pixels(ImageFormat::RgbaPremul).unwrap();
+        assert_eq!(&pixels[..4], &[0xff, 0, 0, 0xff]);
+    }
+
+    #[test]
+    fn raw_pixels_rgba_separate() {
+        let device = Device::new().unwrap();
+        let mut target = device.bitmap_target(4, 4, 1.0).unwrap();
+        {
+            let mut piet = target.render_context();
+            let red = Color::rgba8(0xff, 0, 0, 0x80);
+            piet.fill(Rect::new(0.0, 0.0, 4.0, 4.0), &red);
+            piet.finish().unwrap();
+        }
+        let premul = target.get_raw_pixels(ImageFormat::RgbaPremul).unwrap();
+        assert_eq!(&premul[..4], &[0x80, 0, 0, 0x80]);
+        let separate = target.get_raw_pixels(ImageFormat::RgbaSeparate).unwrap();
+        assert_eq!(&separate[..4], &[0xff, 0, 0, 0x80]);
+    }
+
+    #[test]
+    fn raw_pixels_rgb() {
+        let device = Device::new().unwrap();
+        let mut target = device.bitmap_target(3, 2, 1.0).unwrap();
+        {
+            let mut piet = target.render_context();
+            piet.fill(Rect::new(0.0, 0.0, 1.0, 2.0), &Color::rgb8(0xff, 0, 0));
+            piet.fill(Rect::new(1.0, 0.0, 2.0, 2.0), &Color::rgb8(0, 0xff, 0));
+            piet.fill(Rect::new(2.0, 0.0, 3.0, 2.0), &Color::rgb8(0, 0, 0xff));
+            piet.finish().unwrap();
+        }
+        let pixels = target.get_raw_pixels(ImageFormat::Rgb).unwrap();
+        assert_eq!(pixels.len(), 3 * 2 * 3);
+        assert_eq!(&pixels[..9], &[0xff, 0, 0, 0, 0xff, 0, 0, 0, 0xff]);
+    }
+
+    #[test]
+    fn raw_pixels_grayscale() {
+        let device = Device::new().unwrap();
+        let mut target = device.bitmap_target(5, 3, 1.0).unwrap();
+        {
+            let mut piet = target.render_context();
+            let gradient = piet::FixedLinearGradient {
+                start: (0.0, 0.0).into(),
+                end: (5.0, 0.0).into(),
+                stops: piet::GradientStops::to_vec((Color::BLACK, Color::WHITE)),
+                extend: piet::GradientExtend::Pad,
+            };
+            let brush = piet.gradient(gradient).unwrap();
+            piet.fill(Rect::new(0.0, 0.0, 5.0, 3.0), &brush);
+            piet.finish().unwrap();
+        }
+        let pixels = target.get_raw_pixels(ImageFormat::Grayscale).unwrap();
+        assert_eq!(pixels.len(), 5 * 3);
+        // the middle of the second row
+        let mid = pixels[5 + 2];
+        assert!((mid as i32 - 128).abs() <= 2, "{}", mid);
+        assert!(pixels[5] < mid && mid < pixels[5 + 4]);
+    }
+
+    #[test]
+    fn raw_pixels_then_render() {
+        let device = Device::new().unwrap();
+        let mut target = device.bitmap_target(2, 1, 1.0).unwrap();
+        {
+            let mut piet = target.render_context();
+            piet.fill(Rect::new(0.0, 0.0, 1.0, 1.0), &Color::WHITE);
+            piet.finish().unwrap();
+        }
+        target.get_raw_pixels(ImageFormat::RgbaPremul).unwrap();
+        target.get_raw_pixels(ImageFormat::RgbaPremul).unwrap();
+        {
+            let mut piet = target.render_context();
+            piet.fill(Rect::new(1.0, 0.0, 2.0, 1.0), &Color::WHITE);
+            piet.finish().unwrap();
+        }
+        let pixels = target.get_raw_pixels(ImageFormat::RgbaPremul).unwrap();
+        assert_eq!(pixels, [0xff; 8]);
+    }
+
+    #[test]
+    fn capture_image_area() {
+        let device = Device::new().unwrap();
+        let mut target = device.bitmap_target(8, 4, 1.0).unwrap();
+        {
+            let mut piet = target.render_context();
+            piet.clear(Color::WHITE);
+            piet.fill(Rect::new(0.0, 0.0, 4.0, 4.0), &Color::rgb8(0xff, 0, 0));
+            let image = piet
+                .capture_image_area(Rect::new(0.0, 0.0, 4.0, 4.0))
+                .unwrap();
+            piet.draw_image(
+                &image,
+                Rect::new(4.0, 0.0, 8.0, 4.0),
+                piet::InterpolationMode::NearestNeighbor,
+            );
+            piet.finish().unwrap();
+        }
+        let pixels = target.into_raw_pixels(ImageFormat::RgbaPremul).unwrap();
+        assert_eq!(&pixels[7 * 4..8 * 4], &[0xff, 0, 0, 0xff]);
+    }
+
+    #[test]
+    fn stroke_dash_odd() {
+        let device = Device::new().unwrap();
+        let mut target = device.bitmap_target(12, 1, 1.0).unwrap();
+        {
+            let mut piet = target.render_context();
+            let mut style = piet::StrokeStyle::new();
+            style.set_dash(vec![3.0, 1.0, 2.0], 0.0);
+            let line = piet::kurbo::Line::new((0.0, 0.5), (12.0, 0.5));
+            piet.stroke_styled(line, &Color::BLACK, 1.0, &style);
+            piet.finish().unwrap();
+        }
+        let pixels = target.into_raw_pixels(ImageFormat::RgbaPremul).unwrap();
+        let alpha = pixels.chunks(4).map(|p| p[3] > 0x80).collect::<Vec<_>>();
+        // 3 on, 1 off, 2 on, then the pattern repeats with the lengths swapped
+        let expected = [1, 1, 1, 0, 1, 1, 0, 0, 0, 1, 0, 0];
+        assert_eq!(
+            alpha,
+            expected.iter().map(|&on| on == 1).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn with_save_restores_clip() {
+        let device = Device::new().unwrap();
+        let mut target = device.bitmap_target(4, 1, 1.0).unwrap();
+        {
+            let mut piet = target.render_context();
+            piet.with_save(|piet| {
+                piet.clip(Rect::new(0.0, 0.0, 2.0, 1.0));
+                piet.fill(Rect::new(0.0, 0.0, 4.0, 1.0), &Color::WHITE);
+                Err(piet::new_error(ErrorKind::InvalidInput))
+            })
+            .unwrap_err();
+            piet.fill(Rect::new(3.0, 0.0, 4.0, 1.0), &Color::WHITE);
+            piet.finish().unwrap();
+        }
+        let pixels = target.get_raw_pixels(ImageFormat::Grayscale).unwrap();
+        assert_eq!(pixels, vec![0xff, 0xff, 0, 0xff]);
+    }
+
+    #[test]
+    fn shared_device() {
+        let device = std::sync::Arc::new(Device::new().unwrap());
+        let threads: Vec<_> = (0..4)
+            .map(|i| {
+                let device = device.clone();
+                std::thread::spawn(move || {
+                    let mut target = device.bitmap_target(2, 2, 1.0).unwrap();
+                    {
+                        let mut piet = target.render_context();
+                        piet.clear(Color::grey8(i * 16));
+                        piet.finish().unwrap();
+                    }
+                    target.get_raw_pixels(ImageFormat::Grayscale).unwrap()
+                })
+            })
+            .collect();
+        for (i, thread) in threads.into_iter().enumerate() {
+            assert_eq!(thread.join().unwrap(), vec![i as u8 * 16; 4]);
+        }
+    }
+
+    #[test]
+    fn raw_pixels_area() {
+        let device = Device::new().unwrap();
+        let mut target = device.bitmap_target(100, 100, 1.0).unwrap();
+        {
+            let mut piet = target.render_context();
+            piet.fill(Rect::new(50.0, 50.0, 51.0, 51.0), &Color::rgb8(0xff, 0, 0));
+            piet.finish().unwrap();
+        }
+        let area = Rect::new(45.0, 45.0, 55.0, 55.0);
+        let pixels = target
+            .get_raw_pixels_area(area, ImageFormat::RgbaPremul)
+            .unwrap();
+        assert_eq!(pixels.len(), 10 * 10 * 4);
+        let offset = (5 * 10 + 5) * 4;
+        assert_eq!(&pixels[offset..offset + 4], &[0xff, 0, 0, 0xff]);
+        assert_eq!(pixels.iter().filter(|&&b| b != 0).count(), 2);
+
+        // clamped to the bitmap
+        let area = Rect::new(95.0, -5.0, 105.5, 5.0);
+        let pixels = target
+            .get_raw_pixels_area(area, ImageFormat::Grayscale)
+            .unwrap();
+        assert_eq!(pixels.len(), 5 * 5);
+    }
+
+    #[test]
+    fn clear_all() {
+        let device = Device::new().unwrap();
+        let mut target = device.bitmap_target(4, 4, 1.0).unwrap();
+        {
+            let mut piet = target.render_context();
+            piet.fill(Rect::new(0.0, 0.0, 4.0, 4.0), &Color::WHITE);
+            // a clip left over from the frame
+            piet.clip(Rect::new(0.0, 0.0, 1.0, 1.0));
+            piet.finish().unwrap();
+        }
+        target.clear_all();
+        let pixels = target.get_raw_pixels(ImageFormat::RgbaPremul).unwrap();
+        assert!(pixels.iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn dimensions() {
+        let device = Device::new().unwrap();
+        let target = device.bitmap_target(37, 13, 1.0).unwrap();
+        assert_eq!((target.width(), target.height()), (37, 13));
+        assert!(target.stride() >= 37 * 4);
+    }
+
+    #[test]
+    fn surface_error_source() {
+        let device = Device::new().unwrap();
+        // larger than cairo's maximum surface size
+        let err = device.bitmap_target(1 << 16, 1, 1.0).err().unwrap();
+        let source = std::error::Error::source(&err).unwrap();
+        let cairo_err = source.downcast_ref::<CairoError>().unwrap();
+        assert_eq!(cairo_err.0, Status::InvalidSize);
+    }
+
+    #[test]
+    fn pix_scale_physical_size() {
+        let device = Device::new().unwrap();
+        let mut target = device.bitmap_target(100, 100, 2.0).unwrap();
+        assert_eq!((target.width(), target.height()), (200, 200));
+        {
+            let mut piet = target.render_context();
+            piet.fill(Rect::new(0.0, 0.0, 100.0, 100.0), &Color::WHITE);
+            piet.finish().unwrap();
+        }
+        let pixels = target.get_raw_pixels(ImageFormat::Grayscale).unwrap();
+        assert_eq!(pixels.len(), 200 * 200);
+        assert!(pixels.iter().all(|&p| p == 0xff));
+        drop(target);
+
+        let target = device.bitmap_target(3, 5, 1.5).unwrap();
+        assert_eq!((target.width(), target.height()), (5, 8));
+    }
+
+    #[test]
+    fn render_into_borrowed_data() {
+        let device = Device::new().unwrap();
+        let mut data = vec![0; 4 * 2 * 4];
+        {
+            let mut target = device.bitmap_target_for_data(2, 4, 8, &mut data).unwrap();
+            let mut piet = target.render_context();
+            piet.fill(Rect::new(0.0, 0.0, 2.0, 2.0), &Color::rgb8(0xff, 0x80, 0));
+            piet.finish().unwrap();
+        }
+        let pixel = |i: usize| u32::from_ne_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+        assert_eq!(pixel(0), 0xff_ff_80_00);
+        assert_eq!(pixel(12), 0xff_ff_80_00);
+        assert_eq!(pixel(16), 0);
+    }
+
+    #[test]
+    fn borrowed_data_too_small() {
+        let device = Device::new().unwrap();
+        let mut data = vec![0; 15];
+        assert!(device.bitmap_target_for_data(2, 2, 8, &mut data).is_err());
+        assert!(device.bitmap_target_for_data(2, 1, 4, &mut data).is_err());
+        // cairo rejects strides that aren't suitably aligned
+        assert!(device.bitmap_target_for_data(1, 1, 5, &mut data).is_err());
+        // sizes whose byte counts overflow, or that don't fit in cairo's dimensions
+        assert!(device
+            .bitmap_target_for_data(1, usize::MAX / 2, 4, &mut data)
+            .is_err());
+        assert!(device
+            .bitmap_target_for_data(usize::MAX / 2, 1, 4, &mut data)
+            .is_err());
+        assert!(device
+            .bitmap_target_for_data(1, 0, usize::MAX, &mut data)
+            .is_err());
+    }
+
+    #[test]
+    fn make_image_wrong_size() {
+        let device = Device::new().unwrap();
+        let mut target = device.bitmap_target(2, 2, 1.0).unwrap();
+        let mut piet = target.render_context();
+        let invalid =
+            |res: Result<_, piet::Error>| res.err().unwrap().to_string() == "Invalid input";
+        // short by one byte for 3 and 4 bytes per pixel
+        assert!(invalid(piet.make_image(2, 2, &[0; 11], ImageFormat::Rgb)));
+        assert!(invalid(piet.make_image(
+            2,
+            2,
+            &[0; 15],
+            ImageFormat::RgbaPremul
+        )));
+        // dimensions whose byte count overflows
+        assert!(invalid(piet.make_image(
+            usize::MAX / 2,
+            3,
+            &[0; 12],
+            ImageFormat::Rgb
+        )));
+        let gray = piet.make_image(2, 2, &[0; 4], ImageFormat::Grayscale);
+        assert!(gray.is_err() && !invalid(gray));
+        assert!(piet.make_image(2, 2, &[0; 12], ImageFormat::Rgb).is_ok());
+        piet.finish().unwrap();
+    }
+
+    #[test]
+    fn a8_target_grayscale() {
+        let device = Device::new().unwrap();
+        let mut target = device
+            .bitmap_target_with_format(4, 4, 1.0, Format::A8)
+            .unwrap();
+        {
+            let mut piet = target.render_context();
+            piet.fill(Rect::new(0.0, 0.0, 2.0, 4.0), &Color::WHITE.with_alpha(0.5));
+            piet.finish().unwrap();
+        }
+        let pixels = target.get_raw_pixels(ImageFormat::Grayscale).unwrap();
+        assert_eq!(pixels.len(), 16);
+        assert_eq!(&pixels[..4], &[0x80, 0x80, 0, 0]);
+        let pixels = target.get_raw_pixels(ImageFormat::RgbaPremul).unwrap();
+        assert_eq!(&pixels[..4], &[0, 0, 0, 0x80]);
+    }
+
+    #[test]
+    fn rgb24_target_is_opaque() {
+        let device = Device::new().unwrap();
+        let mut target = device
+            .bitmap_target_with_format(2, 2, 1.0, Format::Rgb24)
+            .unwrap();
+        {
+            let mut piet = target.render_context();
+            piet.clear(Color::rgb8(0x10, 0x20, 0x30));
+            piet.finish().unwrap();
+        }
+        let pixels = target.get_raw_pixels(ImageFormat::RgbaSeparate).unwrap();
+        assert_eq!(&pixels[..4], &[0x10, 0x20, 0x30, 0xff]);
+    }
+
+    #[test]
+    #[cfg(feature = "png")]
+    fn save_to_file_straight_alpha() {
+        let device = Device::new().unwrap();
+        let mut target = device.bitmap_target(2, 2, 1.0).unwrap();
+        {
+            let mut piet = target.render_context();
+            let white = Color::WHITE.with_alpha(0.5);
+            piet.fill(Rect::new(0.0, 0.0, 2.0, 2.0), &white);
+            piet.finish().unwrap();
+        }
+        let path = std::env::temp_dir().join("piet-common-save-to-file-straight-alpha.png");
+        target.save_to_file(&path).unwrap();
+
+        let decoder = png::Decoder::new(File::open(&path).unwrap());
+        let (info, mut reader) = decoder.read_info().unwrap();
+        let mut pixels = vec![0; info.buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(&pixels[..4], &[0xff, 0xff, 0xff, 0x80]);
+    }
+
+    #[test]
+    #[cfg(feature = "png")]
+    fn save_to_writer_round_trip() {
+        let device = Device::new().unwrap();
+        let mut target = device.bitmap_target(3, 2, 1.0).unwrap();
+        let mut bytes = Vec::new();
+        target.save_to_writer(&mut bytes).unwrap();
+
+        let decoder = png::Decoder::new(bytes.as_slice());
+        let (info, _) = decoder.read_info().unwrap();
+        assert_eq!((info.width, info.height), (3, 2));
+    }
+
+    #[test]
+    #[cfg(feature = "png")]
+    fn save_to_file_16bit() {
+        let device = Device::new().unwrap();
+        let mut target = device.bitmap_target(2, 2, 1.0).unwrap();
+        {
+            let mut piet = target.render_context();
+            piet.clear(Color::rgb8(0x80, 0, 0xff));
+            piet.finish().unwrap();
+        }
+        let path = std::env::temp_dir().join("piet-common-save-to-file-16bit.png");
+        target.save_to_file_16bit(&path).unwrap();
+
+        let mut decoder = png::Decoder::new(File::open(&path).unwrap());
+        // keep the full 16 bits
+        decoder.set_transformations(png::Transformations::IDENTITY);
+        let (info, mut reader) = decoder.read_info().unwrap();
+        let mut pixels = vec![0; info.buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(info.bit_depth, png::BitDepth::Sixteen);
+        assert_eq!(pixels.len(), 2 * 2 * 4 * 2);
+        let channel = |i: usize| u16::from_be_bytes([pixels[i * 2], pixels[i * 2 + 1]]);
+        assert_eq!(channel(0), 0x80 * 257);
+        assert_eq!(channel(2), 0xffff);
+        assert_eq!(channel(3), 0xffff);
+    }
+
+    #[test]
+    #[cfg(feature = "pdf")]
+    fn pdf_target() {
+        use piet::{FontBuilder, Text, TextLayoutBuilder};
+
+        let device = Device::new().unwrap();
+        let path = std::env::temp_dir().join("piet-common-pdf-target.pdf");
+        let mut target = device.pdf_target(100.0, 50.0, &path).unwrap();
+        {
+            let mut piet = target.render_context();
+            piet.fill(Rect::new(10.0, 10.0, 40.0, 40.0), &Color::rgb8(0xff, 0, 0));
+            let font = piet
+                .text()
+                .new_font_by_name("sans-serif", 12.0)
+                .build()
+                .unwrap();
+            let layout = piet
+                .text()
+                .new_text_layout(&font, "piet", None)
+                .build()
+                .unwrap();
+            piet.draw_text(&layout, (50.0, 30.0), &Color::BLACK);
+            piet.finish().unwrap();
+        }
+        target.finish().unwrap();
+
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(data.starts_with(b"%PDF"));
+    }
+
+    #[test]
+    #[cfg(feature = "png")]
+    fn save_to_file_error_source() {
+        let device = Device::new().unwrap();
+        let mut target = device.bitmap_target(16, 16, 1.0).unwrap();
+        let err = target
+            .save_to_file("/nonexistent/dir/image.png")
+            .unwrap_err();
+        let source = std::error::Error::source(&err).unwrap();
+        let io_err = source.downcast_ref::<std::io::Error>().unwrap();
+        assert_eq!(io_err.kind(), std::io::ErrorKind::NotFound);
+    }
 }
@@ -0,0 +1,193 @@
+//! Conversions between pixel formats.
+
+use piet::{Error, ErrorKind, ImageFormat};
+
+/// Convert pixels from one format to another.
+///
+/// The rows of `src` start every `stride` bytes, while the rows of the returned
+/// pixels are tightly packed. Converting to `ImageFormat::Rgb` composites the
+/// pixels over black, and converting to `ImageFormat::Grayscale` discards alpha.
+///
+/// Returns an `InvalidInput` error if `src` is too short for the given
+/// dimensions.
+pub fn convert(
+    src: &[u8],
+    src_fmt: ImageFormat,
+    dst_fmt: ImageFormat,
+    width: usize,
+    height: usize,
+    stride: usize,
+) -> Result<Vec<u8>, Error> {
+    let src_bpp = bytes_per_pixel(src_fmt)?;
+    let dst_bpp = bytes_per_pixel(dst_fmt)?;
+    if height > 0
+        && (stride < width * src_bpp || src.len() < (height - 1) * stride + width * src_bpp)
+    {
+        return Err(piet::new_error(ErrorKind::InvalidInput));
+    }
+
+    let mut dst = Vec::with_capacity(width * height * dst_bpp);
+    for y in 0..height {
+        let row = &src[y * stride..][..width * src_bpp];
+        if src_fmt == dst_fmt {
+            dst.extend_from_slice(row);
+            continue;
+        }
+        for pixel in row.chunks(src_bpp) {
+            match dst_fmt {
+                ImageFormat::RgbaPremul => dst.extend_from_slice(&premul_rgba(pixel, src_fmt)),
+                ImageFormat::RgbaSeparate => dst.extend_from_slice(&separate_rgba(pixel, src_fmt)),
+                // premultiplied colors are already composited over black
+                ImageFormat::Rgb => dst.extend_from_slice(&premul_rgba(pixel, src_fmt)[..3]),
+                _ => {
+                    let [r, g, b, _] = separate_rgba(pixel, src_fmt);
+                    dst.push(luminance(r, g, b));
+                }
+            }
+        }
+    }
+    Ok(dst)
+}
+
+fn bytes_per_pixel(fmt: ImageFormat) -> Result<usize, Error> {
+    match fmt {
+        ImageFormat::Rgb
+        | ImageFormat::RgbaSeparate
+        | ImageFormat::RgbaPremul
+        | ImageFormat::Grayscale => Ok(fmt.bytes_per_pixel()),
+        _ => Err(piet::new_error(ErrorKind::NotSupported)),
+    }
+}
+
+/// A pixel of the given format as RGBA with premultiplied alpha.
+fn premul_rgba(pixel: &[u8], fmt: ImageFormat) -> [u8; 4] {
+    match fmt {
+        ImageFormat::RgbaPremul => [pixel[0], pixel[1], pixel[2], pixel[3]],
+        ImageFormat::RgbaSeparate => {
+            let a = pixel[3];
+            [
+                premul(pixel[0], a),
+                premul(pixel[1], a),
+                premul(pixel[2], a),
+                a,
+            ]
+        }
+        _ => opaque_rgba(pixel, fmt),
+    }
+}
+
+/// A pixel of the given format as RGBA with separate alpha.
+fn separate_rgba(pixel: &[u8], fmt: ImageFormat) -> [u8; 4] {
+    match fmt {
+        ImageFormat::RgbaSeparate => [pixel[0], pixel[1], pixel[2], pixel[3]],
+        ImageFormat::RgbaPremul => {
+            let a = pixel[3];
+            [
+                unpremul(pixel[0], a),
+                unpremul(pixel[1], a),
+                unpremul(pixel[2], a),
+                a,
+            ]
+        }
+        _ => opaque_rgba(pixel, fmt),
+    }
+}
+
+/// A pixel of a format without alpha as opaque RGBA.
+fn opaque_rgba(pixel: &[u8], fmt: ImageFormat) -> [u8; 4] {
+    if fmt == ImageFormat::Grayscale {
+        [pixel[0], pixel[0], pixel[0], 0xff]
+    } else {
+        [pixel[0], pixel[1], pixel[2], 0xff]
+    }
+}
+
+/// Apply alpha premultiplication to a color channel, rounding to the nearest value.
+fn premul(x: u8, a: u8) -> u8 {
+    ((x as u16 * a as u16 + 127) / 255) as u8
+}
+
+/// Undo alpha premultiplication of a color channel, rounding to the nearest value.
+fn unpremul(x: u8, a: u8) -> u8 {
+    if a == 0 {
+        0
+    } else {
+        ((x as u16 * 255 + a as u16 / 2) / a as u16).min(255) as u8
+    }
+}
+
+/// The luminance of a color, with Rec. 601 weights.
+fn luminance(r: u8, g: u8, b: u8) -> u8 {
+    (0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PREMUL: [u8; 8] = [0x80, 0x40, 0x00, 0x80, 0x10, 0x20, 0x30, 0x00];
+    const SEPARATE: [u8; 8] = [0xff, 0x80, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00];
+
+    fn convert_row(src: &[u8], src_fmt: ImageFormat, dst_fmt: ImageFormat) -> Vec<u8> {
+        let width = src.len() / src_fmt.bytes_per_pixel();
+        convert(src, src_fmt, dst_fmt, width, 1, src.len()).unwrap()
+    }
+
+    #[test]
+    fn premul_to_separate() {
+        let pixels = convert_row(&PREMUL, ImageFormat::RgbaPremul, ImageFormat::RgbaSeparate);
+        // fully transparent pixels have no color
+        assert_eq!(pixels, SEPARATE);
+    }
+
+    #[test]
+    fn separate_to_premul() {
+        let pixels = convert_row(
+            &SEPARATE,
+            ImageFormat::RgbaSeparate,
+            ImageFormat::RgbaPremul,
+        );
+        assert_eq!(pixels, [0x80, 0x40, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn rgba_to_rgb() {
+        let pixels = convert_row(&PREMUL, ImageFormat::RgbaPremul, ImageFormat::Rgb);
+        assert_eq!(pixels, [0x80, 0x40, 0x00, 0x10, 0x20, 0x30]);
+        let pixels = convert_row(&SEPARATE, ImageFormat::RgbaSeparate, ImageFormat::Rgb);
+        assert_eq!(pixels, [0x80, 0x40, 0x00, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn rgba_to_grayscale() {
+        let pixels = convert_row(&PREMUL, ImageFormat::RgbaPremul, ImageFormat::Grayscale);
+        // 0.299 * 255 + 0.587 * 128
+        assert_eq!(pixels, [151, 0]);
+        let pixels = convert_row(&SEPARATE, ImageFormat::RgbaSeparate, ImageFormat::Grayscale);
+        assert_eq!(pixels, [151, 0]);
+    }
+
+    #[test]
+    fn opaque_to_rgba() {
+        let pixels = convert_row(
+            &[0x10, 0x20, 0x30],
+            ImageFormat::Rgb,
+            ImageFormat::RgbaPremul,
+        );
+        assert_eq!(pixels, [0x10, 0x20, 0x30, 0xff]);
+        let pixels = convert_row(&[0x40], ImageFormat::Grayscale, ImageFormat::RgbaSeparate);
+        assert_eq!(pixels, [0x40, 0x40, 0x40, 0xff]);
+    }
+
+    #[test]
+    fn stride() {
+        let src = [1, 2, 3, 0, 4, 5, 6, 0];
+        let pixels = convert(&src, ImageFormat::Rgb, ImageFormat::Rgb, 1, 2, 4).unwrap();
+        assert_eq!(pixels, [1, 2, 3, 4, 5, 6]);
+        // the last row needn't be padded
+        let pixels = convert(&src[..7], ImageFormat::Rgb, ImageFormat::Rgb, 1, 2, 4).unwrap();
+        assert_eq!(pixels, [1, 2, 3, 4, 5, 6]);
+        assert!(convert(&src[..6], ImageFormat::Rgb, ImageFormat::Rgb, 1, 2, 4).is_err());
+        assert!(convert(&src, ImageFormat::Rgb, ImageFormat::Rgb, 2, 1, 4).is_err());
+    }
+}
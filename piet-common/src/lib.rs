@@ -20,6 +20,10 @@
 //! backend. The types documented below can be used portable across all
 //! backends.
 //!
+//! With the `svg` feature, the [`any`] module provides a render context that
+//! draws to either a Cairo bitmap or an SVG document, chosen at runtime.
+//!
+//! [`any`]: any/index.html
 //! [piet]: https://crates.io/crates/piet
 //! [kurbo]: https://crates.io/crates/kurbo
 //! [piet-cairo]: https://crates.io/crates/piet-cairo
@@ -47,6 +51,17 @@ cfg_if::cfg_if! {
 
 pub use backend::*;
 
+pub mod image_util;
+
+#[cfg(all(
+    feature = "svg",
+    any(
+        feature = "cairo",
+        not(any(target_arch = "wasm32", feature = "web", target_os = "windows"))
+    )
+))]
+pub mod any;
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -73,7 +73,7 @@ impl Device {
 
     /// Create a new bitmap target.
     pub fn bitmap_target(
-        &mut self,
+        &self,
         width: usize,
         height: usize,
         pix_scale: f64,
@@ -2,14 +2,18 @@
 
 use winapi::um::d2d1::{
     D2D1_CAP_STYLE, D2D1_CAP_STYLE_FLAT, D2D1_CAP_STYLE_ROUND, D2D1_CAP_STYLE_SQUARE, D2D1_COLOR_F,
-    D2D1_DASH_STYLE_CUSTOM, D2D1_DASH_STYLE_SOLID, D2D1_GRADIENT_STOP, D2D1_LINE_JOIN,
+    D2D1_DASH_STYLE_CUSTOM, D2D1_DASH_STYLE_SOLID, D2D1_EXTEND_MODE, D2D1_EXTEND_MODE_CLAMP,
+    D2D1_EXTEND_MODE_MIRROR, D2D1_EXTEND_MODE_WRAP, D2D1_GRADIENT_STOP, D2D1_LINE_JOIN,
     D2D1_LINE_JOIN_BEVEL, D2D1_LINE_JOIN_MITER, D2D1_LINE_JOIN_ROUND, D2D1_MATRIX_3X2_F,
     D2D1_POINT_2F, D2D1_RECT_F, D2D1_STROKE_STYLE_PROPERTIES,
 };
 
 use piet::kurbo::{Affine, Point, Rect, Vec2};
 
-use piet::{Color, Error, GradientStop, LineCap, LineJoin, RoundFrom, RoundInto, StrokeStyle};
+use piet::{
+    Color, Error, GradientExtend, GradientStop, LineCap, LineJoin, RoundFrom, RoundInto,
+    StrokeStyle,
+};
 
 use crate::d2d::D2DFactory;
 
@@ -115,6 +119,14 @@ pub(crate) fn gradient_stop_to_d2d(stop: &GradientStop) -> D2D1_GRADIENT_STOP {
     }
 }
 
+pub(crate) fn convert_extend(extend: GradientExtend) -> D2D1_EXTEND_MODE {
+    match extend {
+        GradientExtend::Pad => D2D1_EXTEND_MODE_CLAMP,
+        GradientExtend::Repeat => D2D1_EXTEND_MODE_WRAP,
+        GradientExtend::Reflect => D2D1_EXTEND_MODE_MIRROR,
+    }
+}
+
 fn convert_line_cap(line_cap: LineCap) -> D2D1_CAP_STYLE {
     match line_cap {
         LineCap::Butt => D2D1_CAP_STYLE_FLAT,
@@ -142,34 +154,34 @@ pub(crate) fn convert_stroke_style(
     stroke_style: &StrokeStyle,
     width: f32,
 ) -> Result<crate::d2d::StrokeStyle, Error> {
+    let stroke_style = stroke_style.resolve();
     #[allow(unused)]
-    let cap = convert_line_cap(stroke_style.line_cap.unwrap_or(LineCap::Butt));
-    let join = convert_line_join(stroke_style.line_join.unwrap_or(LineJoin::Miter));
-    let (dashes, dash_style, dash_off) = match &stroke_style.dash {
-        Some((dashes, off)) => {
-            let width_recip = if width == 0.0 { 1.0 } else { width.recip() };
-            assert!(dashes.len() <= 0xffff_ffff);
-            (
-                Some(
-                    dashes
-                        .iter()
-                        .map(|x| *x as f32 * width_recip)
-                        .collect::<Vec<f32>>(),
-                ),
-                D2D1_DASH_STYLE_CUSTOM,
-                *off as f32,
-            )
-        }
-        None => (None, D2D1_DASH_STYLE_SOLID, 0.0),
+    let cap = convert_line_cap(stroke_style.line_cap);
+    let join = convert_line_join(stroke_style.line_join);
+    let (dashes, dash_style) = if stroke_style.dashes.is_empty() {
+        (None, D2D1_DASH_STYLE_SOLID)
+    } else {
+        let width_recip = if width == 0.0 { 1.0 } else { width.recip() };
+        assert!(stroke_style.dashes.len() <= 0xffff_ffff);
+        (
+            Some(
+                stroke_style
+                    .dashes
+                    .iter()
+                    .map(|x| *x as f32 * width_recip)
+                    .collect::<Vec<f32>>(),
+            ),
+            D2D1_DASH_STYLE_CUSTOM,
+        )
     };
     let props = D2D1_STROKE_STYLE_PROPERTIES {
         startCap: cap,
         endCap: cap,
         dashCap: D2D1_CAP_STYLE_FLAT,
         lineJoin: join,
-        miterLimit: stroke_style.miter_limit.unwrap_or(10.0) as f32,
+        miterLimit: stroke_style.miter_limit as f32,
         dashStyle: dash_style,
-        dashOffset: dash_off,
+        dashOffset: stroke_style.dash_offset as f32,
     };
     Ok(factory.create_stroke_style(&props, dashes.as_deref())?)
 }
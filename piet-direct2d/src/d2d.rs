@@ -24,7 +24,7 @@ use winapi::um::d2d1::{
     ID2D1SolidColorBrush, ID2D1StrokeStyle, D2D1_ANTIALIAS_MODE_PER_PRIMITIVE, D2D1_BEZIER_SEGMENT,
     D2D1_BITMAP_INTERPOLATION_MODE, D2D1_BRUSH_PROPERTIES, D2D1_COLOR_F,
     D2D1_COMPATIBLE_RENDER_TARGET_OPTIONS_NONE, D2D1_DEBUG_LEVEL_WARNING, D2D1_DRAW_TEXT_OPTIONS,
-    D2D1_EXTEND_MODE_CLAMP, D2D1_FACTORY_OPTIONS, D2D1_FACTORY_TYPE_MULTI_THREADED,
+    D2D1_EXTEND_MODE, D2D1_FACTORY_OPTIONS, D2D1_FACTORY_TYPE_MULTI_THREADED,
     D2D1_FIGURE_BEGIN_FILLED, D2D1_FIGURE_BEGIN_HOLLOW, D2D1_FIGURE_END_CLOSED,
     D2D1_FIGURE_END_OPEN, D2D1_FILL_MODE_ALTERNATE, D2D1_FILL_MODE_WINDING, D2D1_GAMMA_2_2,
     D2D1_GRADIENT_STOP, D2D1_LAYER_OPTIONS_NONE, D2D1_LAYER_PARAMETERS,
@@ -436,6 +436,7 @@ impl DeviceContext {
     pub(crate) fn create_gradient_stops(
         &mut self,
         stops: &[D2D1_GRADIENT_STOP],
+        extend: D2D1_EXTEND_MODE,
     ) -> Result<GradientStopCollection, Error> {
         unsafe {
             // Should this assert or should we return an overflow error? Super
@@ -448,7 +449,7 @@ impl DeviceContext {
                 stops.as_ptr(),
                 stops.len() as u32,
                 D2D1_GAMMA_2_2,
-                D2D1_EXTEND_MODE_CLAMP,
+                extend,
                 &mut ptr,
             );
             wrap(hr, ptr, GradientStopCollection)
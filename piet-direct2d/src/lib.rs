@@ -24,8 +24,8 @@ use winapi::um::dcommon::{D2D1_ALPHA_MODE_IGNORE, D2D1_ALPHA_MODE_PREMULTIPLIED}
 use piet::kurbo::{Affine, PathEl, Point, Rect, Shape};
 
 use piet::{
-    new_error, Color, Error, ErrorKind, FixedGradient, ImageFormat, InterpolationMode, IntoBrush,
-    RenderContext, StrokeStyle,
+    new_error, normalize_stops, Color, Error, ErrorKind, FixedGradient, ImageFormat,
+    InterpolationMode, IntoBrush, RenderContext, StrokeStyle,
 };
 
 use crate::d2d::wrap_unit;
@@ -34,8 +34,8 @@ pub use crate::dwrite::DwriteFactory;
 pub use crate::text::{D2DFont, D2DFontBuilder, D2DText, D2DTextLayout, D2DTextLayoutBuilder};
 
 use crate::conv::{
-    affine_to_matrix3x2f, color_to_colorf, convert_stroke_style, gradient_stop_to_d2d,
-    rect_to_rectf, to_point2f,
+    affine_to_matrix3x2f, color_to_colorf, convert_extend, convert_stroke_style,
+    gradient_stop_to_d2d, rect_to_rectf, to_point2f,
 };
 use crate::d2d::{Bitmap, Brush, DeviceContext, FillRule, PathGeometry};
 
@@ -175,8 +175,13 @@ impl<'a> RenderContext for D2DRenderContext<'a> {
                     startPoint: to_point2f(linear.start),
                     endPoint: to_point2f(linear.end),
                 };
-                let stops: Vec<_> = linear.stops.iter().map(gradient_stop_to_d2d).collect();
-                let stops = self.rt.create_gradient_stops(&stops)?;
+                let stops: Vec<_> = normalize_stops(&linear.stops)
+                    .iter()
+                    .map(gradient_stop_to_d2d)
+                    .collect();
+                let stops = self
+                    .rt
+                    .create_gradient_stops(&stops, convert_extend(linear.extend))?;
                 let result = self.rt.create_linear_gradient(&props, &stops)?;
                 Ok(result)
             }
@@ -187,8 +192,13 @@ impl<'a> RenderContext for D2DRenderContext<'a> {
                     radiusX: radial.radius as f32,
                     radiusY: radial.radius as f32,
                 };
-                let stops: Vec<_> = radial.stops.iter().map(gradient_stop_to_d2d).collect();
-                let stops = self.rt.create_gradient_stops(&stops)?;
+                let stops: Vec<_> = normalize_stops(&radial.stops)
+                    .iter()
+                    .map(gradient_stop_to_d2d)
+                    .collect();
+                let stops = self
+                    .rt
+                    .create_gradient_stops(&stops, convert_extend(radial.extend))?;
                 let result = self.rt.create_radial_gradient(&props, &stops)?;
                 Ok(result)
             }
@@ -111,6 +111,19 @@ impl<'a> TextLayoutBuilder for D2DTextLayoutBuilder<'a> {
     }
 }
 
+impl D2DTextLayout {
+    /// Whether the text position is after the start of the trailing whitespace of its line.
+    fn is_trailing_whitespace(&self, text_position: usize) -> bool {
+        self.line_metrics
+            .iter()
+            .take_while(|lm| lm.start_offset <= text_position)
+            .last()
+            .map_or(false, |lm| {
+                text_position > lm.end_offset - lm.trailing_whitespace
+            })
+    }
+}
+
 impl TextLayout for D2DTextLayout {
     fn width(&self) -> f64 {
         self.layout.get_metrics().widthIncludingTrailingWhitespace as f64
@@ -173,7 +186,10 @@ impl TextLayout for D2DTextLayout {
             count_until_utf16(&self.text, text_position_16).unwrap_or_else(|| self.text.len());
 
         HitTestPoint {
-            metrics: HitTestMetrics { text_position },
+            metrics: HitTestMetrics {
+                text_position,
+                is_trailing_whitespace: self.is_trailing_whitespace(text_position),
+            },
             is_inside: htp.is_inside,
         }
     }
@@ -206,6 +222,7 @@ impl TextLayout for D2DTextLayout {
                     },
                     metrics: HitTestMetrics {
                         text_position, // no need to use directwrite return value
+                        is_trailing_whitespace: self.is_trailing_whitespace(text_position),
                     },
                 }
             })
@@ -1,20 +1,22 @@
 //! SVG output support for piet
-//!
-//! Text and images are unimplemented and will always return errors.
 
+mod output;
+mod path;
 mod text;
 
 use std::borrow::Cow;
-use std::{io, mem};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use std::{fmt, io, mem};
 
-use piet::kurbo::{Affine, Point, Rect, Shape};
+use piet::kurbo::{Affine, Point, Rect, Shape, Size};
 use piet::{
-    new_error, Color, Error, ErrorKind, FixedGradient, ImageFormat, InterpolationMode, IntoBrush,
-    LineCap, LineJoin, StrokeStyle,
+    new_error, normalize_stops, BlendMode, Color, Error, ErrorKind, FixedGradient, GradientExtend,
+    GradientStop, ImageFormat, InterpolationMode, IntoBrush, LineCap, LineJoin, StrokeStyle,
 };
 use svg::node::Node;
 
-pub use crate::text::{Text, TextLayout};
+pub use crate::text::{FontStyle, FontWeight, Text, TextAlignment, TextLayout};
 
 type Result<T> = std::result::Result<T, Error>;
 
@@ -22,9 +24,26 @@ type Result<T> = std::result::Result<T, Error>;
 pub struct RenderContext {
     stack: Vec<State>,
     state: State,
-    doc: svg::Document,
+    /// The groups that drawing is currently nested in, innermost last.
+    ///
+    /// The first holds the top-level content of the document, and is never closed.
+    groups: Vec<svg::node::element::Group>,
+    defs: svg::node::element::Definitions,
+    /// Ids of the nodes in `defs`, keyed by their serialization without the id.
+    definitions: HashMap<String, Id>,
     next_id: u64,
+    id_prefix: String,
     text: Text,
+    embed_fonts: bool,
+    embedded_fonts: HashSet<String>,
+    format: Format,
+    /// The size of the document, if known.
+    size: Option<Size>,
+    preserve_aspect_ratio: Option<PreserveAspectRatio>,
+    pretty: bool,
+    minify: bool,
+    title: Option<String>,
+    description: Option<String>,
 }
 
 impl RenderContext {
@@ -34,26 +53,368 @@ impl RenderContext {
         Self {
             stack: Vec::new(),
             state: State::default(),
-            doc: svg::Document::new(),
+            groups: vec![svg::node::element::Group::new()],
+            defs: svg::node::element::Definitions::new(),
+            definitions: HashMap::new(),
             next_id: 0,
+            id_prefix: String::new(),
             text: Text::new(),
+            embed_fonts: false,
+            embedded_fonts: HashSet::new(),
+            format: Format {
+                precision: Some(3),
+                relative_paths: false,
+            },
+            size: None,
+            preserve_aspect_ratio: None,
+            pretty: false,
+            minify: false,
+            title: None,
+            description: None,
+        }
+    }
+
+    /// Construct an empty `RenderContext` for a document of the given size.
+    ///
+    /// The size is written as the `width` and `height` of the document, and as its
+    /// `viewBox`, so that the image scales to fit when it's displayed at another size.
+    pub fn with_size(size: Size) -> Self {
+        Self {
+            size: Some(size),
+            ..Self::new()
+        }
+    }
+
+    /// Set the number of decimal places that coordinates are rounded to.
+    ///
+    /// This applies to paths and other shapes, gradients, and transforms, and is 3 by
+    /// default. `None` writes coordinates at full precision.
+    pub fn set_precision(&mut self, precision: Option<usize>) {
+        self.format.precision = precision;
+    }
+
+    /// Set the `preserveAspectRatio` of the document.
+    ///
+    /// This controls how the image is fitted when it's displayed with a different aspect
+    /// ratio than its `viewBox`. Passing `None`, the default, leaves the attribute out,
+    /// which means `PreserveAspectRatio::Meet(Align::Mid, Align::Mid)`.
+    pub fn set_preserve_aspect_ratio(&mut self, value: Option<PreserveAspectRatio>) {
+        self.preserve_aspect_ratio = value;
+    }
+
+    /// Set a prefix for the ids of gradients, clip paths, and other definitions.
+    ///
+    /// Ids must be unique across an HTML document, so documents that are inlined into
+    /// the same page should each use a different prefix. This only affects definitions
+    /// made afterwards. Empty by default.
+    ///
+    /// The prefix must be the start of an XML name: a letter or `_`, followed by
+    /// letters, digits, `-`, `.` and `_`. Returns an `InvalidInput` error otherwise.
+    pub fn set_id_prefix(&mut self, prefix: &str) -> Result<()> {
+        let is_name_char = |c: char| c.is_alphanumeric() || matches!(c, '-' | '.' | '_');
+        let mut chars = prefix.chars();
+        let valid = match chars.next() {
+            Some(c) => (c.is_alphabetic() || c == '_') && chars.all(is_name_char),
+            None => true,
+        };
+        if !valid {
+            return Err(new_error(ErrorKind::InvalidInput));
+        }
+        self.id_prefix = prefix.to_owned();
+        Ok(())
+    }
+
+    /// Set whether path data is written with relative commands.
+    ///
+    /// Relative commands, which give each point as an offset from the previous one, are
+    /// more compact but harder to read. Disabled by default.
+    pub fn set_relative_paths(&mut self, relative_paths: bool) {
+        self.format.relative_paths = relative_paths;
+    }
+
+    /// Set the title of the document, which is written as its `<title>`.
+    pub fn set_title(&mut self, title: Option<&str>) {
+        self.title = title.map(String::from);
+    }
+
+    /// Set the description of the document, which is written as its `<desc>`.
+    pub fn set_description(&mut self, description: Option<&str>) {
+        self.description = description.map(String::from);
+    }
+
+    /// Set whether the document is written with each element indented by its depth.
+    ///
+    /// This is easier to read and diff. The content of text elements is written as is,
+    /// so the document renders the same either way. Disabled by default.
+    pub fn set_pretty(&mut self, pretty: bool) {
+        self.pretty = pretty;
+    }
+
+    /// Set whether the document is written in its most compact form.
+    ///
+    /// This leaves out the whitespace between elements and attributes that have their
+    /// default values, and writes colors and transforms in their shortest forms. The
+    /// content of text elements is written as is. Together with a low precision and
+    /// relative paths, this gives the smallest output. Takes precedence over
+    /// [`set_pretty`]. Disabled by default.
+    ///
+    /// [`set_pretty`]: #method.set_pretty
+    pub fn set_minify(&mut self, minify: bool) {
+        self.minify = minify;
+    }
+
+    /// Set whether font files are embedded in the document.
+    ///
+    /// When enabled, the first time text is drawn in a font family whose data was
+    /// provided with [`Text::load_font_data`], the font file is embedded as an
+    /// `@font-face` rule, so that the document renders the same regardless of the
+    /// fonts installed where it is viewed. Disabled by default.
+    ///
+    /// [`Text::load_font_data`]: struct.Text.html#method.load_font_data
+    pub fn set_embed_fonts(&mut self, embed_fonts: bool) {
+        self.embed_fonts = embed_fonts;
+    }
+
+    /// Blend subsequent drawing with what is below it, until the current state is restored.
+    ///
+    /// The drawing is grouped and the group is blended as a whole, using the CSS
+    /// `mix-blend-mode` property, so shapes drawn within it aren't blended with each other.
+    /// Setting another mode, including `BlendMode::Normal`, ends the group, but only a
+    /// group started since the state was last saved.
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        let mut reopen = Vec::new();
+        if let Some(index) = self.state.blend_group.take() {
+            // clips and transforms since the blend group still apply after it
+            while self.groups.len() > index + 1 {
+                reopen.push(empty_group(self.groups.last().unwrap()));
+                self.close_group();
+            }
+            self.close_group();
+        }
+        let keyword = match mode {
+            BlendMode::Normal => None,
+            BlendMode::Multiply => Some("multiply"),
+            BlendMode::Screen => Some("screen"),
+            BlendMode::Overlay => Some("overlay"),
+            BlendMode::Darken => Some("darken"),
+            BlendMode::Lighten => Some("lighten"),
+            BlendMode::ColorDodge => Some("color-dodge"),
+            BlendMode::ColorBurn => Some("color-burn"),
+            BlendMode::HardLight => Some("hard-light"),
+            BlendMode::SoftLight => Some("soft-light"),
+            BlendMode::Difference => Some("difference"),
+            BlendMode::Exclusion => Some("exclusion"),
+            BlendMode::Hue => Some("hue"),
+            BlendMode::Saturation => Some("saturation"),
+            BlendMode::Color => Some("color"),
+            BlendMode::Luminosity => Some("luminosity"),
+        };
+        if let Some(keyword) = keyword {
+            self.state.blend_group = Some(self.groups.len());
+            self.open_group(
+                svg::node::element::Group::new()
+                    .set("style", format!("mix-blend-mode: {}", keyword)),
+            );
+        }
+        for group in reopen.into_iter().rev() {
+            self.open_group(group);
+        }
+    }
+
+    /// Create a brush that tiles an image.
+    ///
+    /// Each tile is `tile.size()`, and one of them has its origin at `tile.origin()`,
+    /// in the coordinate space of the shapes the brush is used to draw.
+    ///
+    /// Tiled image brushes aren't part of the piet API, so this is specific to SVG.
+    pub fn image_pattern(&mut self, image: &Image, tile: Rect) -> Brush {
+        let node = svg::node::element::Image::new()
+            .set("width", tile.width())
+            .set("height", tile.height())
+            .set("preserveAspectRatio", "none")
+            .set("href", image.href.as_str());
+        let pattern = svg::node::element::Pattern::new()
+            .set("patternUnits", "userSpaceOnUse")
+            .set("x", tile.x0)
+            .set("y", tile.y0)
+            .set("width", tile.width())
+            .set("height", tile.height())
+            .add(node);
+        Brush {
+            kind: BrushKind::Ref(self.define(pattern)),
         }
     }
 
     /// Write graphics rendered so far to an `std::io::Write` impl, such as `std::fs::File`
     ///
+    /// The document is written out piece by piece as it's serialized, without being
+    /// built in memory first; to get it as a `String` instead, use `to_string`.
     /// Additional rendering can be done afterwards.
-    pub fn write(&self, writer: impl io::Write) -> io::Result<()> {
-        svg::write(writer, &self.doc)
+    pub fn write(&self, mut writer: impl io::Write) -> io::Result<()> {
+        write!(writer, "{}", self)
+    }
+
+    /// Serialize the document rendered so far, the way the svg crate would.
+    fn write_document(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        let mut doc = svg::Document::new();
+        if let Some(size) = self.size {
+            doc.assign("width", size.width);
+            doc.assign("height", size.height);
+            doc.assign("viewBox", (0, 0, size.width, size.height));
+        }
+        if let Some(value) = self.preserve_aspect_ratio {
+            doc.assign("preserveAspectRatio", value.to_string());
+        }
+        let title = self
+            .title
+            .as_ref()
+            .map(|title| svg::node::element::Title::new().add(svg::node::Text::new(escape(title))));
+        let description = self.description.as_ref().map(|description| {
+            svg::node::element::Description::new().add(svg::node::Text::new(escape(description)))
+        });
+        let has_defs = !self.defs.get_inner().get_children().is_empty();
+        let (root, nested) = self.groups.split_first().unwrap();
+        let content = root.get_inner().get_children();
+        if title.is_none()
+            && description.is_none()
+            && !has_defs
+            && content.is_empty()
+            && nested.is_empty()
+        {
+            return write!(f, "{}", doc);
+        }
+        write_open_tag(f, doc.get_inner())?;
+        if let Some(title) = title {
+            write!(f, "\n{}", title)?;
+        }
+        if let Some(description) = description {
+            write!(f, "\n{}", description)?;
+        }
+        if has_defs {
+            write!(f, "\n{}", self.defs)?;
+        }
+        // the root group only holds the content, so it isn't written itself
+        for node in content {
+            write!(f, "\n{}", node)?;
+        }
+        write_groups(f, nested)?;
+        f.write_str("\n</svg>")
+    }
+
+    /// Embed the font's data in the document, if needed.
+    fn embed_font(&mut self, font: &text::Font) {
+        let data = match font.data {
+            Some(ref data) if self.embed_fonts => data,
+            _ => return,
+        };
+        if !self.embedded_fonts.insert(font.family.clone()) {
+            return;
+        }
+        let (mime, format) = match data.get(..4) {
+            Some(b"wOFF") => ("font/woff", "woff"),
+            Some(b"wOF2") => ("font/woff2", "woff2"),
+            Some(b"OTTO") => ("font/otf", "opentype"),
+            _ => ("font/ttf", "truetype"),
+        };
+        let family = font.family.replace('\\', "\\\\").replace('"', "\\\"");
+        let css = format!(
+            r#"@font-face {{ font-family: "{}"; src: url(data:{};base64,{}) format("{}"); }}"#,
+            family,
+            mime,
+            base64::encode(data),
+            format,
+        );
+        self.content()
+            .append(svg::node::element::Style::new(escape(&css)));
+    }
+
+    /// Add a node to the document's `<defs>`, returning its id.
+    ///
+    /// Identical definitions are only added once.
+    fn define(&mut self, mut node: impl Node) -> Id {
+        let key = node.to_string();
+        if let Some(id) = self.definitions.get(&key) {
+            return id.clone();
+        }
+        let id = self.new_id();
+        node.assign("id", id.clone());
+        self.defs.append(node);
+        self.definitions.insert(key, id.clone());
+        id
+    }
+
+    /// The group that drawing is currently added to.
+    fn content(&mut self) -> &mut svg::node::element::Group {
+        self.groups.last_mut().unwrap()
+    }
+
+    /// Nest subsequent drawing in `group`, until the current state is restored.
+    fn open_group(&mut self, group: svg::node::element::Group) {
+        self.groups.push(group);
+    }
+
+    fn close_group(&mut self) {
+        let group = self.groups.pop().unwrap();
+        self.content().append(group);
     }
 
     fn new_id(&mut self) -> Id {
-        let x = Id(self.next_id);
+        let x = Id::new(&self.id_prefix, self.next_id);
         self.next_id += 1;
         x
     }
 }
 
+impl fmt::Display for RenderContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let style = if self.minify {
+            output::Style::Minified
+        } else if self.pretty {
+            output::Style::Pretty
+        } else {
+            return self.write_document(f);
+        };
+        let mut writer = output::Writer::new(f, style);
+        self.write_document(&mut writer)?;
+        writer.finish()
+    }
+}
+
+/// Write the groups, each nested in the one before it, as the svg crate would if they
+/// were appended to each other.
+fn write_groups(f: &mut impl fmt::Write, groups: &[svg::node::element::Group]) -> fmt::Result {
+    let (group, nested) = match groups.split_first() {
+        Some(split) => split,
+        None => return Ok(()),
+    };
+    if nested.is_empty() {
+        return write!(f, "\n{}", group);
+    }
+    f.write_str("\n")?;
+    write_open_tag(f, group.get_inner())?;
+    for node in group.get_inner().get_children() {
+        write!(f, "\n{}", node)?;
+    }
+    write_groups(f, nested)?;
+    f.write_str("\n</g>")
+}
+
+/// Write the opening tag of an element that has children, as the svg crate would.
+fn write_open_tag(f: &mut impl fmt::Write, element: &svg::node::element::Element) -> fmt::Result {
+    write!(f, "<{}", element.get_name())?;
+    let mut attributes = element.get_attributes().iter().collect::<Vec<_>>();
+    attributes.sort_by_key(|pair| pair.0.as_str());
+    for (name, value) in attributes {
+        match (value.contains('\''), value.contains('"')) {
+            (_, false) => write!(f, r#" {}="{}""#, name, value)?,
+            (false, true) => write!(f, " {}='{}'", name, value)?,
+            // the svg crate leaves out attributes it can't quote
+            (true, true) => {}
+        }
+    }
+    f.write_str(">")
+}
+
 impl piet::RenderContext for RenderContext {
     type Brush = Brush;
 
@@ -72,10 +433,17 @@ impl piet::RenderContext for RenderContext {
             .set("width", "100%")
             .set("height", "100%")
             .set("fill", brush.val());
-        if let Some(id) = self.state.clip {
-            rect.assign("clip-path", format!("url(#{})", id.to_string()));
+        if let Some(opacity) = brush.opacity() {
+            rect.assign("fill-opacity", opacity);
+        }
+        // clearing covers the whole canvas, regardless of the current transform
+        if !is_identity(&self.state.xf) {
+            rect.assign(
+                "transform",
+                xf_val(&self.state.xf.inverse(), self.format.precision),
+            );
         }
-        self.doc.append(rect);
+        self.content().append(rect);
     }
 
     fn solid_brush(&mut self, color: Color) -> Brush {
@@ -85,44 +453,39 @@ impl piet::RenderContext for RenderContext {
     }
 
     fn gradient(&mut self, gradient: impl Into<FixedGradient>) -> Result<Brush> {
-        let id = self.new_id();
-        match gradient.into() {
+        let precision = self.format.precision;
+        let r = |x| round(x, precision);
+        let id = match gradient.into() {
             FixedGradient::Linear(x) => {
                 let mut gradient = svg::node::element::LinearGradient::new()
                     .set("gradientUnits", "userSpaceOnUse")
-                    .set("id", id)
-                    .set("x1", x.start.x)
-                    .set("y1", x.start.y)
-                    .set("x2", x.end.x)
-                    .set("y2", x.end.y);
-                for stop in x.stops {
-                    gradient.append(
-                        svg::node::element::Stop::new()
-                            .set("offset", stop.pos)
-                            .set("stop-color", fmt_color(&stop.color)),
-                    );
-                }
-                self.doc.append(gradient);
+                    .set("x1", r(x.start.x))
+                    .set("y1", r(x.start.y))
+                    .set("x2", r(x.end.x))
+                    .set("y2", r(x.end.y));
+                set_extend(&mut gradient, x.extend);
+                add_stops(&mut gradient, &x.stops);
+                self.define(gradient)
             }
             FixedGradient::Radial(x) => {
+                // SVG 1.1 moves a focal point outside the circle onto its edge, as browsers
+                // do; make that explicit so that all viewers agree.
+                let mut offset = x.origin_offset;
+                if offset.hypot() > x.radius {
+                    offset *= x.radius / offset.hypot();
+                }
                 let mut gradient = svg::node::element::RadialGradient::new()
                     .set("gradientUnits", "userSpaceOnUse")
-                    .set("id", id)
-                    .set("cx", x.center.x)
-                    .set("cy", x.center.y)
-                    .set("fx", x.center.x + x.origin_offset.x)
-                    .set("fy", x.center.y + x.origin_offset.y)
-                    .set("r", x.radius);
-                for stop in x.stops {
-                    gradient.append(
-                        svg::node::element::Stop::new()
-                            .set("offset", stop.pos)
-                            .set("stop-color", fmt_color(&stop.color)),
-                    );
-                }
-                self.doc.append(gradient);
+                    .set("cx", r(x.center.x))
+                    .set("cy", r(x.center.y))
+                    .set("fx", r(x.center.x + offset.x))
+                    .set("fy", r(x.center.y + offset.y))
+                    .set("r", r(x.radius));
+                set_extend(&mut gradient, x.extend);
+                add_stops(&mut gradient, &x.stops);
+                self.define(gradient)
             }
-        }
+        };
         Ok(Brush {
             kind: BrushKind::Ref(id),
         })
@@ -130,60 +493,40 @@ impl piet::RenderContext for RenderContext {
 
     fn fill(&mut self, shape: impl Shape, brush: &impl IntoBrush<Self>) {
         let brush = brush.make_brush(self, || shape.bounding_box());
-        add_shape(
-            &mut self.doc,
-            shape,
-            &Attrs {
-                xf: self.state.xf,
-                clip: self.state.clip,
-                fill: Some((brush.into_owned(), None)),
-                ..Attrs::default()
-            },
-        );
+        let format = self.format;
+        let attrs = Attrs {
+            fill: Some((brush.into_owned(), None)),
+            ..Attrs::default()
+        };
+        add_shape(self.content(), shape, &attrs, format);
     }
 
     fn fill_even_odd(&mut self, shape: impl Shape, brush: &impl IntoBrush<Self>) {
         let brush = brush.make_brush(self, || shape.bounding_box());
-        add_shape(
-            &mut self.doc,
-            shape,
-            &Attrs {
-                xf: self.state.xf,
-                clip: self.state.clip,
-                fill: Some((brush.into_owned(), Some("evenodd"))),
-                ..Attrs::default()
-            },
-        );
+        let format = self.format;
+        let attrs = Attrs {
+            fill: Some((brush.into_owned(), Some("evenodd"))),
+            ..Attrs::default()
+        };
+        add_shape(self.content(), shape, &attrs, format);
     }
 
     fn clip(&mut self, shape: impl Shape) {
-        let id = self.new_id();
-        let mut clip = svg::node::element::ClipPath::new().set("id", id);
-        add_shape(
-            &mut clip,
-            shape,
-            &Attrs {
-                xf: self.state.xf,
-                clip: self.state.clip,
-                ..Attrs::default()
-            },
-        );
-        self.doc.append(clip);
-        self.state.clip = Some(id);
+        let mut clip = svg::node::element::ClipPath::new();
+        add_shape(&mut clip, shape, &Attrs::default(), self.format);
+        // Nesting groups intersects their clips.
+        let id = self.define(clip);
+        self.open_group(svg::node::element::Group::new().set("clip-path", format!("url(#{})", id)));
     }
 
     fn stroke(&mut self, shape: impl Shape, brush: &impl IntoBrush<Self>, width: f64) {
         let brush = brush.make_brush(self, || shape.bounding_box());
-        add_shape(
-            &mut self.doc,
-            shape,
-            &Attrs {
-                xf: self.state.xf,
-                clip: self.state.clip,
-                stroke: Some((brush.into_owned(), width, &StrokeStyle::new())),
-                ..Attrs::default()
-            },
-        );
+        let format = self.format;
+        let attrs = Attrs {
+            stroke: Some((brush.into_owned(), width, &StrokeStyle::new())),
+            ..Attrs::default()
+        };
+        add_shape(self.content(), shape, &attrs, format);
     }
 
     fn stroke_styled(
@@ -194,16 +537,12 @@ impl piet::RenderContext for RenderContext {
         style: &StrokeStyle,
     ) {
         let brush = brush.make_brush(self, || shape.bounding_box());
-        add_shape(
-            &mut self.doc,
-            shape,
-            &Attrs {
-                xf: self.state.xf,
-                clip: self.state.clip,
-                stroke: Some((brush.into_owned(), width, style)),
-                ..Attrs::default()
-            },
-        );
+        let format = self.format;
+        let attrs = Attrs {
+            stroke: Some((brush.into_owned(), width, style)),
+            ..Attrs::default()
+        };
+        add_shape(self.content(), shape, &attrs, format);
     }
 
     fn text(&mut self) -> &mut Self::Text {
@@ -212,15 +551,62 @@ impl piet::RenderContext for RenderContext {
 
     fn draw_text(
         &mut self,
-        _layout: &Self::TextLayout,
-        _pos: impl Into<Point>,
-        _brush: &impl IntoBrush<Self>,
+        layout: &Self::TextLayout,
+        pos: impl Into<Point>,
+        brush: &impl IntoBrush<Self>,
     ) {
-        unimplemented!()
+        let pos = pos.into();
+        self.embed_font(&layout.font);
+        let brush = brush.make_brush(self, || {
+            Rect::from_origin_size(pos, (layout.width, layout.font.size))
+        });
+        let mut text = svg::node::element::Text::new()
+            .set("font-family", escape(&layout.font.family))
+            .set("font-size", layout.font.size);
+        match layout.font.weight {
+            FontWeight::Normal => {}
+            FontWeight::Bold => text.assign("font-weight", "bold"),
+        }
+        match layout.font.style {
+            FontStyle::Normal => {}
+            FontStyle::Italic => text.assign("font-style", "italic"),
+            FontStyle::Oblique => text.assign("font-style", "oblique"),
+        }
+        match layout.alignment {
+            TextAlignment::Start => {}
+            TextAlignment::Center => text.assign("text-anchor", "middle"),
+            TextAlignment::End => text.assign("text-anchor", "end"),
+        }
+        let x = pos.x + layout.anchor_offset();
+        Attrs {
+            fill: Some((brush.into_owned(), None)),
+            ..Attrs::default()
+        }
+        .apply_to(&mut text);
+        // Each line is positioned absolutely on its own baseline, the first of which is at
+        // `pos`. A relative `dy` would be lost on empty lines, which have no characters to
+        // apply it to.
+        for lm in &layout.line_metrics {
+            let line = &layout.text[lm.start_offset..lm.end_offset - lm.trailing_whitespace];
+            // svg 0.7 has no dedicated `tspan` type
+            let mut tspan = svg::node::element::Element::new("tspan");
+            tspan.assign("x", x);
+            tspan.assign("y", pos.y + lm.cumulative_height - lm.height);
+            // Renderers collapse runs of spaces and strip leading ones, which would make
+            // the line narrower than its layout, but they keep non-breaking spaces.
+            let line = line.replace(' ', "\u{a0}");
+            tspan.append(svg::node::Text::new(escape(&line)));
+            text.append(tspan);
+        }
+        self.content().append(text);
     }
 
     fn save(&mut self) -> Result<()> {
-        let new = self.state.clone();
+        self.state.depth = self.groups.len();
+        let new = State {
+            blend_group: None,
+            ..self.state.clone()
+        };
         self.stack.push(mem::replace(&mut self.state, new));
         Ok(())
     }
@@ -230,15 +616,25 @@ impl piet::RenderContext for RenderContext {
             .stack
             .pop()
             .ok_or_else(|| new_error(ErrorKind::StackUnbalance))?;
+        while self.groups.len() > self.state.depth {
+            self.close_group();
+        }
         Ok(())
     }
 
     fn finish(&mut self) -> Result<()> {
+        if !self.stack.is_empty() {
+            return Err(new_error(ErrorKind::StackUnbalance));
+        }
         Ok(())
     }
 
     fn transform(&mut self, transform: Affine) {
         self.state.xf *= transform;
+        self.open_group(
+            svg::node::element::Group::new()
+                .set("transform", xf_val(&transform, self.format.precision)),
+        );
     }
 
     fn current_transform(&self) -> Affine {
@@ -247,12 +643,12 @@ impl piet::RenderContext for RenderContext {
 
     fn make_image(
         &mut self,
-        _width: usize,
-        _height: usize,
-        _buf: &[u8],
-        _format: ImageFormat,
+        width: usize,
+        height: usize,
+        buf: &[u8],
+        format: ImageFormat,
     ) -> Result<Self::Image> {
-        Err(new_error(ErrorKind::NotSupported))
+        Image::new(width, height, buf, format)
     }
 
     #[inline]
@@ -276,25 +672,109 @@ impl piet::RenderContext for RenderContext {
         draw_image(self, image, Some(src_rect.into()), dst_rect.into(), interp);
     }
 
-    fn blurred_rect(&mut self, _rect: Rect, _blur_radius: f64, _brush: &impl IntoBrush<Self>) {
-        unimplemented!()
+    fn blurred_rect(&mut self, rect: Rect, blur_radius: f64, brush: &impl IntoBrush<Self>) {
+        let brush = brush.make_brush(self, || rect);
+        // The default filter region only extends 10% past the bounding box, which would
+        // clip the blur, so cover the full extent of the Gaussian instead.
+        let region = rect.inflate(BLUR_EXTENT * blur_radius, BLUR_EXTENT * blur_radius);
+        let mut blur = svg::node::element::Element::new("feGaussianBlur");
+        blur.assign("stdDeviation", blur_radius);
+        let filter = svg::node::element::Filter::new()
+            .set("filterUnits", "userSpaceOnUse")
+            .set("x", region.x0)
+            .set("y", region.y0)
+            .set("width", region.width())
+            .set("height", region.height())
+            .add(blur);
+        let id = self.define(filter);
+        let format = self.format;
+        let attrs = Attrs {
+            filter: Some(id),
+            fill: Some((brush.into_owned(), None)),
+            ..Attrs::default()
+        };
+        add_shape(self.content(), rect, &attrs, format);
     }
 }
 
+/// Draw an image, or the `src_rect` area of it.
+///
+/// An area is drawn by nesting the full image in an `<svg>` element whose view box is
+/// `src_rect`, so the rest of the image is clipped away by the viewport.
 fn draw_image(
-    _ctx: &mut RenderContext,
-    _image: &<RenderContext as piet::RenderContext>::Image,
-    _src_rect: Option<Rect>,
-    _dst_rect: Rect,
-    _interp: InterpolationMode,
+    ctx: &mut RenderContext,
+    image: &<RenderContext as piet::RenderContext>::Image,
+    src_rect: Option<Rect>,
+    dst_rect: Rect,
+    interp: InterpolationMode,
 ) {
-    unimplemented!()
+    let mut node = svg::node::element::Image::new()
+        .set("preserveAspectRatio", "none")
+        .set("href", image.href.as_str());
+    // `auto`, the default, smooths the image
+    match interp {
+        InterpolationMode::Bilinear => {}
+        InterpolationMode::NearestNeighbor => node.assign("image-rendering", "pixelated"),
+    }
+    match src_rect {
+        None => {
+            node.assign("x", dst_rect.x0);
+            node.assign("y", dst_rect.y0);
+            node.assign("width", dst_rect.width());
+            node.assign("height", dst_rect.height());
+            ctx.content().append(node);
+        }
+        Some(src_rect) => {
+            node.assign("width", image.width);
+            node.assign("height", image.height);
+            let mut viewport = svg::node::element::Element::new("svg");
+            viewport.assign("x", dst_rect.x0);
+            viewport.assign("y", dst_rect.y0);
+            viewport.assign("width", dst_rect.width());
+            viewport.assign("height", dst_rect.height());
+            viewport.assign(
+                "viewBox",
+                (
+                    src_rect.x0,
+                    src_rect.y0,
+                    src_rect.width(),
+                    src_rect.height(),
+                ),
+            );
+            viewport.assign("preserveAspectRatio", "none");
+            viewport.append(node);
+            ctx.content().append(viewport);
+        }
+    }
 }
 
+fn set_extend(gradient: &mut impl Node, extend: GradientExtend) {
+    match extend {
+        GradientExtend::Pad => {}
+        GradientExtend::Repeat => gradient.assign("spreadMethod", "repeat"),
+        GradientExtend::Reflect => gradient.assign("spreadMethod", "reflect"),
+    }
+}
+
+/// Add the gradient's stops, in order, with offsets in the range [0, 1].
+fn add_stops(gradient: &mut impl Node, stops: &[GradientStop]) {
+    for stop in normalize_stops(stops) {
+        let mut node = svg::node::element::Stop::new()
+            .set("offset", stop.pos)
+            .set("stop-color", fmt_color(&stop.color));
+        if let Some(opacity) = fmt_opacity(&stop.color) {
+            node.assign("stop-opacity", opacity);
+        }
+        gradient.append(node);
+    }
+}
+
+/// Extent of a blur, in multiples of its standard deviation.
+const BLUR_EXTENT: f64 = 3.0;
+
 #[derive(Default)]
 struct Attrs<'a> {
-    xf: Affine,
-    clip: Option<Id>,
+    filter: Option<Id>,
     fill: Option<(Brush, Option<&'a str>)>,
     stroke: Option<(Brush, f64, &'a StrokeStyle)>,
 }
@@ -303,12 +783,14 @@ impl Attrs<'_> {
     // allow clippy warning for `width != 1.0` in if statement
     #[allow(clippy::float_cmp)]
     fn apply_to(&self, node: &mut impl Node) {
-        node.assign("transform", xf_val(&self.xf));
-        if let Some(id) = self.clip {
-            node.assign("clip-path", format!("url(#{})", id.to_string()));
+        if let Some(ref id) = self.filter {
+            node.assign("filter", format!("url(#{})", id));
         }
         if let Some((ref brush, rule)) = self.fill {
             node.assign("fill", brush.val());
+            if let Some(opacity) = brush.opacity() {
+                node.assign("fill-opacity", opacity);
+            }
             if let Some(rule) = rule {
                 node.assign("fill-rule", rule);
             }
@@ -317,42 +799,47 @@ impl Attrs<'_> {
         }
         if let Some((ref stroke, width, style)) = self.stroke {
             node.assign("stroke", stroke.val());
+            if let Some(opacity) = stroke.opacity() {
+                node.assign("stroke-opacity", opacity);
+            }
             if width != 1.0 {
                 node.assign("stroke-width", width);
             }
+            let style = style.resolve();
             match style.line_join {
-                None | Some(LineJoin::Miter) => {}
-                Some(LineJoin::Round) => {
+                LineJoin::Miter => {}
+                LineJoin::Round => {
                     node.assign("stroke-linejoin", "round");
                 }
-                Some(LineJoin::Bevel) => {
+                LineJoin::Bevel => {
                     node.assign("stroke-linejoin", "bevel");
                 }
             }
             match style.line_cap {
-                None | Some(LineCap::Butt) => {}
-                Some(LineCap::Round) => {
+                LineCap::Butt => {}
+                LineCap::Round => {
                     node.assign("stroke-linecap", "round");
                 }
-                Some(LineCap::Square) => {
+                LineCap::Square => {
                     node.assign("stroke-linecap", "square");
                 }
             }
-            if let Some((ref array, offset)) = style.dash {
-                node.assign("stroke-dasharray", array.clone());
-                if offset != 0.0 {
-                    node.assign("stroke-dashoffset", offset);
+            if !style.dashes.is_empty() {
+                node.assign("stroke-dasharray", style.dashes);
+                if style.dash_offset != 0.0 {
+                    node.assign("stroke-dashoffset", style.dash_offset);
                 }
             }
-            if let Some(limit) = style.miter_limit {
-                node.assign("stroke-miterlimit", limit);
+            // piet's default miter limit is 10, but SVG's is 4
+            if style.line_join == LineJoin::Miter && style.miter_limit != 4.0 {
+                node.assign("stroke-miterlimit", style.miter_limit);
             }
         }
     }
 }
 
-fn xf_val(xf: &Affine) -> svg::node::Value {
-    let xf = xf.as_coeffs();
+fn xf_val(xf: &Affine, precision: Option<usize>) -> svg::node::Value {
+    let xf = xf.as_coeffs().map(|x| round(x, precision));
     format!(
         "matrix({} {} {} {} {} {})",
         xf[0], xf[1], xf[2], xf[3], xf[4], xf[5]
@@ -360,51 +847,143 @@ fn xf_val(xf: &Affine) -> svg::node::Value {
     .into()
 }
 
-fn add_shape(node: &mut impl Node, shape: impl Shape, attrs: &Attrs) {
+/// Round `x` to `precision` decimal places, if it's provided.
+fn round(x: f64, precision: Option<usize>) -> f64 {
+    let x = match precision {
+        Some(precision) => {
+            let scale = 10f64.powi(precision as i32);
+            (x * scale).round() / scale
+        }
+        None => x,
+    };
+    // adding zero turns negative zeros, as produced by rounding or inverting, into positive ones
+    x + 0.0
+}
+
+// allow clippy warning for exact comparison, as only an exact identity can be omitted
+#[allow(clippy::float_cmp)]
+fn is_identity(xf: &Affine) -> bool {
+    xf.as_coeffs() == [1.0, 0.0, 0.0, 1.0, 0.0, 0.0]
+}
+
+fn add_shape(node: &mut impl Node, shape: impl Shape, attrs: &Attrs, format: Format) {
+    let r = |x| round(x, format.precision);
     if let Some(circle) = shape.as_circle() {
         let mut x = svg::node::element::Circle::new()
-            .set("cx", circle.center.x)
-            .set("cy", circle.center.y)
-            .set("r", circle.radius);
+            .set("cx", r(circle.center.x))
+            .set("cy", r(circle.center.y))
+            .set("r", r(circle.radius));
+        attrs.apply_to(&mut x);
+        node.append(x);
+    } else if let Some(line) = shape.as_line() {
+        let mut x = svg::node::element::Line::new()
+            .set("x1", r(line.p0.x))
+            .set("y1", r(line.p0.y))
+            .set("x2", r(line.p1.x))
+            .set("y2", r(line.p1.y));
         attrs.apply_to(&mut x);
         node.append(x);
     } else if let Some(rect) = shape.as_rounded_rect() {
+        // kurbo's rounded rects have the same radius at every corner
         let mut x = svg::node::element::Rectangle::new()
-            .set("x", rect.origin().x)
-            .set("y", rect.origin().y)
-            .set("width", rect.width())
-            .set("height", rect.height())
-            .set("rx", rect.radius())
-            .set("ry", rect.radius());
+            .set("x", r(rect.origin().x))
+            .set("y", r(rect.origin().y))
+            .set("width", r(rect.width()))
+            .set("height", r(rect.height()));
+        if rect.radius() > 0.0 {
+            x.assign("rx", r(rect.radius()));
+            x.assign("ry", r(rect.radius()));
+        }
         attrs.apply_to(&mut x);
         node.append(x);
     } else if let Some(rect) = shape.as_rect() {
         let mut x = svg::node::element::Rectangle::new()
-            .set("x", rect.origin().x)
-            .set("y", rect.origin().y)
-            .set("width", rect.width())
-            .set("height", rect.height());
+            .set("x", r(rect.origin().x))
+            .set("y", r(rect.origin().y))
+            .set("width", r(rect.width()))
+            .set("height", r(rect.height()));
         attrs.apply_to(&mut x);
         node.append(x);
     } else {
-        let mut path = svg::node::element::Path::new().set("d", shape.into_bez_path(1e-3).to_svg());
+        let mut path = svg::node::element::Path::new()
+            .set("d", path::path_data(&shape.into_bez_path(1e-3), format));
         attrs.apply_to(&mut path);
         node.append(path)
     }
 }
 
+/// How a document is fitted into a viewport with a different aspect ratio.
+///
+/// This is written as the `preserveAspectRatio` attribute of the document.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PreserveAspectRatio {
+    /// Scale the document to fill the viewport, changing its aspect ratio.
+    None,
+    /// Scale the document to fit inside the viewport, aligned horizontally and vertically
+    /// as given.
+    Meet(Align, Align),
+    /// Scale the document to cover the viewport, aligned horizontally and vertically as
+    /// given.
+    Slice(Align, Align),
+}
+
+/// The alignment of a document within its viewport, along one axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    /// The left or top edges are aligned (`xMin` or `YMin`).
+    Min,
+    /// The centers are aligned (`xMid` or `YMid`).
+    Mid,
+    /// The right or bottom edges are aligned (`xMax` or `YMax`).
+    Max,
+}
+
+impl fmt::Display for PreserveAspectRatio {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = |align| match align {
+            Align::Min => "Min",
+            Align::Mid => "Mid",
+            Align::Max => "Max",
+        };
+        match *self {
+            PreserveAspectRatio::None => f.write_str("none"),
+            PreserveAspectRatio::Meet(x, y) => write!(f, "x{}Y{} meet", name(x), name(y)),
+            PreserveAspectRatio::Slice(x, y) => write!(f, "x{}Y{} slice", name(x), name(y)),
+        }
+    }
+}
+
+/// How numbers and path data are written.
+#[derive(Debug, Clone, Copy)]
+struct Format {
+    precision: Option<usize>,
+    relative_paths: bool,
+}
+
 #[derive(Debug, Clone, Default)]
 struct State {
     xf: Affine,
-    clip: Option<Id>,
+    /// The number of open groups when the state was saved.
+    depth: usize,
+    /// The index of the group with the blend mode, if it was set since the last save.
+    blend_group: Option<usize>,
 }
 
 /// An SVG brush
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct Brush {
     kind: BrushKind,
 }
 
+impl fmt::Debug for Brush {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            BrushKind::Solid(ref color) => write!(f, "Brush::Solid({:?})", color),
+            BrushKind::Ref(ref id) => write!(f, "Brush::Ref(#{})", id),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 enum BrushKind {
     Solid(Color),
@@ -415,7 +994,15 @@ impl Brush {
     fn val(&self) -> svg::node::Value {
         match self.kind {
             BrushKind::Solid(ref color) => fmt_color(color).into(),
-            BrushKind::Ref(id) => format!("url(#{})", id.to_string()).into(),
+            BrushKind::Ref(ref id) => format!("url(#{})", id).into(),
+        }
+    }
+
+    /// The opacity of a solid brush, if it isn't fully opaque.
+    fn opacity(&self) -> Option<svg::node::Value> {
+        match self.kind {
+            BrushKind::Solid(ref color) => fmt_opacity(color),
+            BrushKind::Ref(_) => None,
         }
     }
 }
@@ -430,25 +1017,127 @@ impl IntoBrush<RenderContext> for Brush {
     }
 }
 
+/// A group with the same attributes as `group`, but no content.
+fn empty_group(group: &svg::node::element::Group) -> svg::node::element::Group {
+    let mut empty = svg::node::element::Group::new();
+    for (name, value) in group.get_inner().get_attributes() {
+        empty.assign(name.as_str(), value.clone());
+    }
+    empty
+}
+
+/// Escape the characters that are special in XML text and attribute values.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Format the color as `#rrggbb`; the alpha is emitted separately, see [`fmt_opacity`].
 fn fmt_color(color: &Color) -> String {
     match color {
-        Color::Rgba32(x) => format!("#{:08x}", x),
+        Color::Rgba32(x) => format!("#{:06x}", x >> 8),
+    }
+}
+
+/// The alpha of the color as an opacity value, if it isn't fully opaque.
+///
+/// This is the shortest decimal that maps back to the same alpha byte.
+fn fmt_opacity(color: &Color) -> Option<svg::node::Value> {
+    let alpha = match color {
+        Color::Rgba32(x) => x & 0xff,
+    };
+    if alpha == 0xff {
+        return None;
+    }
+    // three decimal places are always enough
+    let opacity = [10.0, 100.0, 1000.0]
+        .iter()
+        .map(|scale| (alpha as f64 / 255.0 * scale).round() / scale)
+        .find(|opacity| (opacity * 255.0).round() as u32 == alpha)
+        .unwrap();
+    Some(opacity.into())
+}
+
+/// SVG image
+///
+/// The pixels are stored as a PNG, embedded in the document as a data URI wherever the
+/// image is drawn.
+pub struct Image {
+    width: usize,
+    height: usize,
+    href: String,
+}
+
+impl Image {
+    fn new(width: usize, height: usize, buf: &[u8], format: ImageFormat) -> Result<Image> {
+        let (color, bytes_per_pixel) = match format {
+            ImageFormat::Rgb => (png::ColorType::RGB, 3),
+            ImageFormat::RgbaSeparate | ImageFormat::RgbaPremul => (png::ColorType::RGBA, 4),
+            _ => return Err(new_error(ErrorKind::NotSupported)),
+        };
+        let expected_len = width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(bytes_per_pixel));
+        if expected_len != Some(buf.len()) {
+            return Err(new_error(ErrorKind::InvalidInput));
+        }
+        let pixels = match format {
+            ImageFormat::RgbaPremul => Cow::Owned(unpremultiply(buf)),
+            _ => Cow::Borrowed(buf),
+        };
+        let mut data = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut data, width as u32, height as u32);
+            encoder.set_color(color);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder
+                .write_header()
+                .and_then(|mut writer| writer.write_image_data(&pixels))
+                .map_err(Into::<Box<_>>::into)?;
+        }
+        Ok(Image {
+            width,
+            height,
+            href: format!("data:image/png;base64,{}", base64::encode(&data)),
+        })
     }
 }
 
-/// SVG image (unimplemented)
-pub struct Image(());
+/// Convert RGBA pixels with premultiplied alpha to separate alpha, as PNG requires.
+fn unpremultiply(buf: &[u8]) -> Vec<u8> {
+    let mut out = buf.to_vec();
+    for pixel in out.chunks_exact_mut(4) {
+        let a = pixel[3] as u32;
+        for c in &mut pixel[..3] {
+            if let Some(x) = (*c as u32 * 255 + a / 2).checked_div(a) {
+                *c = x.min(255) as u8;
+            }
+        }
+    }
+    out
+}
 
-#[derive(Debug, Copy, Clone)]
-struct Id(u64);
+/// The id of an element in `defs`.
+#[derive(Debug, Clone)]
+struct Id(Rc<str>);
 
 impl Id {
-    // TODO allowing clippy warning temporarily. But this should be changed to impl Display
-    #[allow(clippy::inherent_to_string)]
-    fn to_string(self) -> String {
+    /// The id of the `n`th definition, starting with `prefix`.
+    fn new(prefix: &str, n: u64) -> Id {
         const ALPHABET: &[u8; 52] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
-        let mut out = String::with_capacity(4);
-        let mut x = self.0;
+        let mut out = String::with_capacity(prefix.len() + 4);
+        out.push_str(prefix);
+        let mut x = n;
         loop {
             let digit = (x % ALPHABET.len() as u64) as usize;
             out.push(ALPHABET[digit] as char);
@@ -457,7 +1146,13 @@ impl Id {
                 break;
             }
         }
-        out
+        Id(out.into())
+    }
+}
+
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
     }
 }
 
@@ -466,3 +1161,1054 @@ impl From<Id> for svg::node::Value {
         x.to_string().into()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use piet::kurbo::{BezPath, Circle, Line, RoundedRect, Vec2};
+    use piet::{
+        Color, FixedLinearGradient, FixedRadialGradient, FontBuilder, GradientStops,
+        LinearGradient, RenderContext as _, Text as _, TextLayoutBuilder, UnitPoint,
+    };
+
+    use super::*;
+
+    fn render(f: impl FnOnce(&mut RenderContext)) -> String {
+        let mut ctx = RenderContext::new();
+        f(&mut ctx);
+        let mut out = Vec::new();
+        ctx.write(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    /// Lay out `text` in a sans-serif font of the given size.
+    fn text_layout(ctx: &mut RenderContext, text: &str, size: f64) -> TextLayout {
+        let font = ctx
+            .text()
+            .new_font_by_name("sans-serif", size)
+            .build()
+            .unwrap();
+        ctx.text()
+            .new_text_layout(&font, text, None)
+            .build()
+            .unwrap()
+    }
+
+    /// Render `text` in a sans-serif font of the given size, at `pos`.
+    fn render_text(text: &str, size: f64, pos: impl Into<Point>) -> String {
+        render(|ctx| {
+            let layout = text_layout(ctx, text, size);
+            ctx.draw_text(&layout, pos, &Color::BLACK);
+        })
+    }
+
+    #[test]
+    fn draw_image() {
+        let svg = render(|ctx| {
+            let image = ctx
+                .make_image(2, 2, &[0xff; 2 * 2 * 4], ImageFormat::RgbaSeparate)
+                .unwrap();
+            ctx.draw_image(
+                &image,
+                Rect::new(10.0, 20.0, 50.0, 60.0),
+                InterpolationMode::Bilinear,
+            );
+        });
+        assert!(svg.contains("<image"));
+        assert!(svg.contains(r#"href="data:image/png;base64,"#));
+        assert!(svg.contains(r#"width="40" x="10" y="20""#));
+        assert!(svg.contains(r#"height="40""#));
+        assert!(!svg.contains("image-rendering"));
+    }
+
+    #[test]
+    fn draw_image_area() {
+        let svg = render(|ctx| {
+            let image = ctx
+                .make_image(2, 2, &[0xff; 2 * 2 * 3], ImageFormat::Rgb)
+                .unwrap();
+            ctx.draw_image_area(
+                &image,
+                Rect::new(1.0, 0.0, 2.0, 1.0),
+                Rect::new(10.0, 20.0, 50.0, 60.0),
+                InterpolationMode::NearestNeighbor,
+            );
+        });
+        assert!(svg.contains(r#"viewBox="1 0 1 1""#));
+        assert!(svg.contains(r#"height="40" preserveAspectRatio="none""#));
+        assert!(svg.contains(r#"width="40" x="10" y="20""#));
+        assert!(svg.contains(r#"height="2" href="data:image/png;base64,"#));
+        assert!(svg.contains(r#"image-rendering="pixelated""#));
+    }
+
+    #[test]
+    fn linear_gradient() {
+        let svg = render(|ctx| {
+            let gradient = FixedGradient::Linear(FixedLinearGradient {
+                start: Point::new(0.0, 0.0),
+                end: Point::new(10.0, 0.0),
+                stops: vec![
+                    GradientStop {
+                        pos: 0.0,
+                        color: Color::BLACK,
+                    },
+                    GradientStop {
+                        pos: 1.0,
+                        color: Color::WHITE,
+                    },
+                ],
+                extend: GradientExtend::Pad,
+            });
+            let brush = ctx.gradient(gradient.clone()).unwrap();
+            ctx.fill(Rect::new(0.0, 0.0, 10.0, 10.0), &brush);
+            let brush = ctx.gradient(gradient).unwrap();
+            ctx.fill(Rect::new(0.0, 10.0, 10.0, 20.0), &brush);
+        });
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\">\n<defs>\n"));
+        assert_eq!(svg.matches("<linearGradient").count(), 1);
+        assert!(svg.contains(
+            r#"<linearGradient gradientUnits="userSpaceOnUse" id="a" x1="0" x2="10" y1="0" y2="0">"#
+        ));
+        assert_eq!(svg.matches("<stop ").count(), 2);
+        assert_eq!(svg.matches(r#"fill="url(#a)""#).count(), 2);
+    }
+
+    #[test]
+    fn gradient_stops() {
+        let svg = render(|ctx| {
+            let stops = vec![
+                GradientStop {
+                    pos: 1.5,
+                    color: Color::rgb8(0, 0, 0xff),
+                },
+                GradientStop {
+                    pos: 0.25,
+                    color: Color::rgba8(0, 0xff, 0, 0x66),
+                },
+                GradientStop {
+                    pos: -0.5,
+                    color: Color::rgb8(0xff, 0, 0),
+                },
+            ];
+            ctx.gradient(FixedGradient::Linear(FixedLinearGradient {
+                start: Point::new(0.0, 0.0),
+                end: Point::new(10.0, 0.0),
+                stops,
+                extend: GradientExtend::Pad,
+            }))
+            .unwrap();
+        });
+        assert!(
+            svg.contains(concat!(
+                "<stop offset=\"0\" stop-color=\"#55aa00\" stop-opacity=\"0.6\"/>\n",
+                "<stop offset=\"0.25\" stop-color=\"#00ff00\" stop-opacity=\"0.4\"/>\n",
+                "<stop offset=\"1\" stop-color=\"#006699\" stop-opacity=\"0.76\"/>\n",
+            )),
+            "{}",
+            svg
+        );
+    }
+
+    #[test]
+    fn gradient_extend() {
+        let svg = render(|ctx| {
+            let gradient = LinearGradient::new(
+                UnitPoint::LEFT,
+                UnitPoint::CENTER,
+                (Color::BLACK, Color::WHITE),
+            );
+            ctx.fill(Rect::new(0.0, 0.0, 10.0, 10.0), &gradient);
+            let gradient = gradient.with_extend(GradientExtend::Repeat);
+            ctx.fill(Rect::new(0.0, 0.0, 10.0, 10.0), &gradient);
+        });
+        assert_eq!(svg.matches("spreadMethod").count(), 1);
+        assert!(svg.contains(
+            r#"<linearGradient gradientUnits="userSpaceOnUse" id="b" spreadMethod="repeat""#
+        ));
+    }
+
+    #[test]
+    fn radial_gradient() {
+        let svg = render(|ctx| {
+            let stops = vec![
+                GradientStop {
+                    pos: 0.0,
+                    color: Color::BLACK,
+                },
+                GradientStop {
+                    pos: 1.0,
+                    color: Color::WHITE,
+                },
+            ];
+            let inside = FixedRadialGradient {
+                center: Point::new(10.0, 10.0),
+                origin_offset: Vec2::new(2.0, 0.0),
+                radius: 5.0,
+                stops: stops.clone(),
+                extend: GradientExtend::Pad,
+            };
+            let outside = FixedRadialGradient {
+                origin_offset: Vec2::new(0.0, -20.0),
+                ..inside.clone()
+            };
+            let brush = ctx.gradient(inside).unwrap();
+            ctx.fill(Rect::new(0.0, 0.0, 20.0, 20.0), &brush);
+            let brush = ctx.gradient(outside).unwrap();
+            ctx.fill(Rect::new(0.0, 0.0, 20.0, 20.0), &brush);
+        });
+        assert!(svg.contains(
+            r#"<radialGradient cx="10" cy="10" fx="12" fy="10" gradientUnits="userSpaceOnUse" id="a" r="5">"#
+        ));
+        assert!(svg.contains(r#"fx="10" fy="5""#));
+        assert!(svg.contains(r#"fill="url(#b)""#));
+    }
+
+    #[test]
+    fn size() {
+        let mut ctx = RenderContext::with_size(Size::new(100.0, 50.0));
+        ctx.clear(Color::WHITE);
+        let mut out = Vec::new();
+        ctx.write(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(r#"width="100""#), "{}", out);
+        assert!(out.contains(r#"height="50""#), "{}", out);
+        assert!(out.contains(r#"viewBox="0 0 100 50""#), "{}", out);
+    }
+
+    #[test]
+    fn preserve_aspect_ratio() {
+        let out = render(|ctx| ctx.clear(Color::WHITE));
+        assert!(!out.contains("preserveAspectRatio"), "{}", out);
+
+        let out = render(|ctx| {
+            ctx.set_preserve_aspect_ratio(Some(PreserveAspectRatio::Slice(Align::Min, Align::Max)))
+        });
+        assert!(
+            out.contains(r#"preserveAspectRatio="xMinYMax slice""#),
+            "{}",
+            out
+        );
+
+        let out = render(|ctx| ctx.set_preserve_aspect_ratio(Some(PreserveAspectRatio::None)));
+        assert!(out.contains(r#"preserveAspectRatio="none""#), "{}", out);
+    }
+
+    #[test]
+    fn pretty() {
+        let out = render(|ctx| {
+            ctx.set_pretty(true);
+            ctx.transform(Affine::translate((10.0, 0.0)));
+            ctx.fill(Rect::new(0.0, 0.0, 10.0, 10.0), &Color::BLACK);
+        });
+        assert!(out.contains("\n  <g transform="), "{}", out);
+        assert!(out.contains("\n    <rect "), "{}", out);
+        assert!(out.ends_with("\n  </g>\n</svg>\n"), "{}", out);
+    }
+
+    #[test]
+    fn pretty_text() {
+        fn scene(ctx: &mut RenderContext) {
+            ctx.set_title(Some("a title"));
+            let layout = text_layout(ctx, "hi", 12.0);
+            ctx.draw_text(&layout, (10.0, 20.0), &Color::BLACK);
+        }
+        let compact = render(scene);
+        let pretty = render(|ctx| {
+            ctx.set_pretty(true);
+            scene(ctx);
+        });
+        // text elements aren't indented within
+        for content in &[
+            "<title>\na title\n</title>",
+            "<tspan x=\"10\" y=\"20\">\nhi\n</tspan>",
+        ] {
+            assert!(compact.contains(content), "{}", compact);
+            assert!(pretty.contains(content), "{}", pretty);
+        }
+        assert!(pretty.contains("\n  <title>"), "{}", pretty);
+        assert!(
+            pretty.ends_with("\n</tspan>\n</text>\n</svg>\n"),
+            "{}",
+            pretty
+        );
+    }
+
+    #[test]
+    fn minify() {
+        fn scene(ctx: &mut RenderContext) {
+            ctx.save().unwrap();
+            ctx.transform(Affine::translate((10.0, 0.0)));
+            ctx.fill(Circle::new((5.0, 5.0), 5.0), &Color::BLACK);
+            ctx.fill(Rect::new(0.0, 0.0, 10.0, 10.0), &Color::WHITE);
+            ctx.restore().unwrap();
+        }
+        let pretty = render(|ctx| {
+            ctx.set_pretty(true);
+            scene(ctx);
+        });
+        let minified = render(|ctx| {
+            ctx.set_minify(true);
+            scene(ctx);
+        });
+        assert!(!minified.contains('\n'), "{}", minified);
+        assert!(minified.contains("><circle "), "{}", minified);
+        assert!(
+            minified.contains(r#"transform="translate(10)""#),
+            "{}",
+            minified
+        );
+        assert!(minified.contains(r##"fill="#fff""##), "{}", minified);
+        assert!(minified.len() < pretty.len());
+    }
+
+    #[test]
+    fn minify_defaults() {
+        let out = render(|ctx| {
+            ctx.set_minify(true);
+            let gradient = LinearGradient::new(
+                UnitPoint::LEFT,
+                UnitPoint::RIGHT,
+                (Color::BLACK, Color::WHITE),
+            );
+            ctx.fill(Rect::new(0.0, 0.0, 10.0, 10.0), &gradient);
+        });
+        assert!(out.contains(r##"<stop stop-color="#000"/>"##), "{}", out);
+        assert!(
+            out.contains(r##"<stop offset="1" stop-color="#fff"/>"##),
+            "{}",
+            out
+        );
+    }
+
+    #[test]
+    fn minify_text() {
+        let out = render(|ctx| {
+            ctx.set_minify(true);
+            ctx.set_title(Some("a title"));
+            let layout = text_layout(ctx, "hi", 12.0);
+            ctx.draw_text(&layout, (0.0, 20.0), &Color::BLACK);
+        });
+        // text content is written as in the compact output
+        assert!(out.contains("<title>\na title\n</title>"), "{}", out);
+        assert!(
+            out.contains("<tspan x=\"0\" y=\"20\">\nhi\n</tspan>"),
+            "{}",
+            out
+        );
+        assert!(out.contains("</tspan>\n</text></svg>"), "{}", out);
+    }
+
+    #[test]
+    fn write_parse() {
+        let mut ctx = RenderContext::new();
+        ctx.fill(Rect::new(0.0, 0.0, 10.0, 10.0), &Color::BLACK);
+        ctx.fill(Circle::new((5.0, 5.0), 5.0), &Color::WHITE);
+        let mut out = Vec::new();
+        ctx.write(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out, ctx.to_string());
+
+        let tags = svg::Parser::new(&out)
+            .filter_map(|event| match event {
+                svg::parser::Event::Tag(name, _, _) => Some(name),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(tags, ["svg", "rect", "circle", "svg"]);
+    }
+
+    #[test]
+    fn write_streams() {
+        /// Counts the bytes it's given, and the most given in a single call.
+        #[derive(Default)]
+        struct Counter {
+            total: usize,
+            max: usize,
+        }
+
+        impl io::Write for Counter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.total += buf.len();
+                self.max = self.max.max(buf.len());
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        for &(pretty, minify) in &[(false, false), (true, false), (false, true)] {
+            let mut ctx = RenderContext::new();
+            ctx.set_pretty(pretty);
+            ctx.set_minify(minify);
+            ctx.set_title(Some("streamed"));
+            for i in 0..100 {
+                let x = f64::from(i);
+                ctx.save().unwrap();
+                ctx.clip(Rect::new(x, 0.0, x + 10.0, 10.0));
+                ctx.fill(Rect::new(x, 0.0, x + 5.0, 5.0), &Color::BLACK);
+            }
+            let mut counter = Counter::default();
+            ctx.write(&mut counter).unwrap();
+            assert_eq!(counter.total, ctx.to_string().len());
+            assert!(counter.total > 10_000);
+            assert!(counter.max < 200, "wrote {} bytes at once", counter.max);
+        }
+    }
+
+    #[test]
+    fn id_prefix() {
+        fn scene(ctx: &mut RenderContext) {
+            ctx.clip(Rect::new(0.0, 0.0, 10.0, 10.0));
+            ctx.fill(Rect::new(0.0, 0.0, 10.0, 10.0), &Color::BLACK);
+        }
+        let a = render(|ctx| {
+            ctx.set_id_prefix("a-").unwrap();
+            scene(ctx);
+        });
+        let b = render(|ctx| {
+            ctx.set_id_prefix("b-").unwrap();
+            scene(ctx);
+        });
+        assert!(a.contains(r#"<clipPath id="a-a">"#), "{}", a);
+        assert!(a.contains(r#"clip-path="url(#a-a)""#), "{}", a);
+        assert!(b.contains(r#"<clipPath id="b-a">"#), "{}", b);
+        assert!(!b.contains("a-a"), "{}", b);
+    }
+
+    #[test]
+    fn id_prefix_invalid() {
+        let mut ctx = RenderContext::new();
+        for prefix in &["a\"", "a<", "a)", "a b", "1a", "-a"] {
+            assert!(ctx.set_id_prefix(prefix).is_err(), "{}", prefix);
+        }
+        assert!(ctx.set_id_prefix("_doc.1-").is_ok());
+        assert!(ctx.set_id_prefix("").is_ok());
+    }
+
+    #[test]
+    fn dedup_defs() {
+        let svg = render(|ctx| {
+            let gradient = |end| {
+                FixedGradient::Linear(FixedLinearGradient {
+                    start: Point::new(0.0, 0.0),
+                    end: Point::new(end, 0.0),
+                    stops: vec![
+                        GradientStop {
+                            pos: 0.0,
+                            color: Color::BLACK,
+                        },
+                        GradientStop {
+                            pos: 1.0,
+                            color: Color::WHITE,
+                        },
+                    ],
+                    extend: GradientExtend::Pad,
+                })
+            };
+            for i in 0..100 {
+                let brush = ctx.gradient(gradient(10.0)).unwrap();
+                ctx.fill(Rect::new(0.0, i as f64, 10.0, i as f64 + 1.0), &brush);
+            }
+            let brush = ctx.gradient(gradient(20.0)).unwrap();
+            ctx.fill(Rect::new(0.0, 0.0, 20.0, 1.0), &brush);
+        });
+        assert_eq!(svg.matches("<linearGradient").count(), 2);
+        assert_eq!(svg.matches(r#"fill="url(#a)""#).count(), 100);
+        assert_eq!(svg.matches(r#"fill="url(#b)""#).count(), 1);
+    }
+
+    #[test]
+    fn title_description() {
+        let svg = render(|ctx| {
+            ctx.set_title(Some("Salt & pepper"));
+            ctx.set_description(Some("<b>"));
+        });
+        assert!(
+            svg.contains("<title>\nSalt &amp; pepper\n</title>"),
+            "{}",
+            svg
+        );
+        assert!(svg.contains("<desc>\n&lt;b&gt;\n</desc>"), "{}", svg);
+    }
+
+    #[test]
+    fn brush_debug() {
+        let mut ctx = RenderContext::new();
+        let brush = ctx.solid_brush(Color::rgb8(0x12, 0x34, 0x56));
+        assert_eq!(format!("{:?}", brush), "Brush::Solid(#123456ff)");
+    }
+
+    #[test]
+    fn path_without_move() {
+        let mut path = BezPath::new();
+        path.line_to((10.0, 0.0));
+        path.close_path();
+        path.close_path();
+        path.line_to((0.0, 10.0));
+        let svg = render(|ctx| ctx.fill(&path, &Color::BLACK));
+        assert!(svg.contains(r#"d="M0 0L10 0ZZL0 10""#), "{}", svg);
+    }
+
+    #[test]
+    fn path_fuzz() {
+        // xorshift, so the sequences are the same on every run
+        fn next(state: &mut u64) -> u64 {
+            *state ^= *state << 13;
+            *state ^= *state >> 7;
+            *state ^= *state << 17;
+            *state
+        }
+        fn point(state: &mut u64) -> Point {
+            let x = (next(state) % 200) as f64 - 100.0;
+            let y = (next(state) % 7) as f64 / 3.0;
+            Point::new(x, y)
+        }
+        let mut state = 0x2545_f491_4f6c_dd1d;
+        let s = &mut state;
+        for _ in 0..1000 {
+            let mut path = BezPath::new();
+            for _ in 0..next(s) % 8 {
+                match next(s) % 5 {
+                    0 => path.move_to(point(s)),
+                    1 => path.line_to(point(s)),
+                    2 => path.quad_to(point(s), point(s)),
+                    3 => path.curve_to(point(s), point(s), point(s)),
+                    _ => path.close_path(),
+                }
+            }
+            for &relative_paths in &[false, true] {
+                let format = Format {
+                    precision: Some(3),
+                    relative_paths,
+                };
+                let d = path::path_data(&path, format);
+                assert!(d.is_empty() || d.starts_with(&['M', 'm'][..]), "{}", d);
+            }
+        }
+    }
+
+    #[test]
+    fn clip() {
+        let svg = render(|ctx| {
+            ctx.clip(Rect::new(0.0, 0.0, 10.0, 10.0));
+            ctx.clip(Rect::new(5.0, 5.0, 20.0, 20.0));
+            ctx.fill(Rect::new(0.0, 0.0, 100.0, 100.0), &Color::BLACK);
+        });
+        assert!(svg.contains("<clipPath id=\"a\">\n<rect"));
+        assert!(svg.contains(concat!(
+            "<g clip-path=\"url(#a)\">\n",
+            "<g clip-path=\"url(#b)\">\n",
+            "<rect fill=\"#000000\" height=\"100\"",
+        )));
+        assert!(svg.ends_with("</g>\n</g>\n</svg>"));
+    }
+
+    #[test]
+    fn clip_restore() {
+        let svg = render(|ctx| {
+            ctx.save().unwrap();
+            ctx.clip(Rect::new(0.0, 0.0, 10.0, 10.0));
+            ctx.fill(Rect::new(0.0, 0.0, 100.0, 100.0), &Color::BLACK);
+            ctx.restore().unwrap();
+            ctx.fill(Rect::new(0.0, 0.0, 100.0, 100.0), &Color::WHITE);
+        });
+        assert!(svg.contains(concat!(
+            "<rect fill=\"#000000\" height=\"100\" width=\"100\" x=\"0\" y=\"0\"/>\n",
+            "</g>\n",
+            "<rect fill=\"#ffffff\"",
+        )));
+    }
+
+    #[test]
+    fn transform() {
+        let svg = render(|ctx| {
+            ctx.transform(Affine::translate((10.0, 20.0)));
+            ctx.transform(Affine::scale(2.0));
+            ctx.fill(Rect::new(0.0, 0.0, 10.0, 10.0), &Color::BLACK);
+            ctx.clear(Color::WHITE);
+        });
+        assert!(svg.contains(concat!(
+            "<g transform=\"matrix(1 0 0 1 10 20)\">\n",
+            "<g transform=\"matrix(2 0 0 2 0 0)\">\n",
+            "<rect fill=\"#000000\" height=\"10\" width=\"10\" x=\"0\" y=\"0\"/>\n",
+        )));
+        assert!(svg.contains(r#"transform="matrix(0.5 0 0 0.5 -5 -10)""#));
+    }
+
+    #[test]
+    fn save_restore() {
+        let svg = render(|ctx| {
+            ctx.save().unwrap();
+            ctx.transform(Affine::translate((10.0, 20.0)));
+            ctx.clip(Rect::new(0.0, 0.0, 10.0, 10.0));
+            ctx.fill(Rect::new(0.0, 0.0, 100.0, 100.0), &Color::BLACK);
+            ctx.restore().unwrap();
+            ctx.fill(Rect::new(0.0, 0.0, 100.0, 100.0), &Color::WHITE);
+            assert_eq!(
+                ctx.current_transform().as_coeffs(),
+                Affine::default().as_coeffs()
+            );
+        });
+        assert!(svg.contains(concat!(
+            "<g transform=\"matrix(1 0 0 1 10 20)\">\n",
+            "<g clip-path=\"url(#a)\">\n",
+            "<rect fill=\"#000000\" height=\"100\" width=\"100\" x=\"0\" y=\"0\"/>\n",
+            "</g>\n",
+            "</g>\n",
+            "<rect fill=\"#ffffff\"",
+        )));
+    }
+
+    #[test]
+    fn with_save_error() {
+        let svg = render(|ctx| {
+            let result = ctx.with_save(|ctx| {
+                ctx.transform(Affine::translate((10.0, 0.0)));
+                Err(new_error(ErrorKind::InvalidInput))
+            });
+            assert!(result.is_err());
+            ctx.fill(Rect::new(0.0, 0.0, 10.0, 10.0), &Color::BLACK);
+            ctx.finish().unwrap();
+        });
+        assert!(svg.contains("10 0)\"/>\n<rect "), "{}", svg);
+    }
+
+    #[test]
+    fn save_restore_unbalanced() {
+        let mut ctx = RenderContext::new();
+        assert!(ctx.restore().is_err());
+        ctx.save().unwrap();
+        assert!(ctx.finish().is_err());
+        ctx.restore().unwrap();
+        assert!(ctx.finish().is_ok());
+    }
+
+    #[test]
+    fn stroke_dash() {
+        let svg = render(|ctx| {
+            let mut style = StrokeStyle::new();
+            style.set_dash(vec![4.0, 2.0], 0.0);
+            ctx.stroke_styled(
+                Line::new((0.0, 0.0), (10.0, 0.0)),
+                &Color::BLACK,
+                1.0,
+                &style,
+            );
+            style.set_dash(Vec::new(), 0.0);
+            ctx.stroke_styled(
+                Line::new((0.0, 0.0), (10.0, 0.0)),
+                &Color::BLACK,
+                1.0,
+                &style,
+            );
+        });
+        assert_eq!(svg.matches(r#"stroke-dasharray="4 2""#).count(), 1);
+        assert_eq!(svg.matches("stroke-dasharray").count(), 1);
+    }
+
+    #[test]
+    fn stroke_dash_odd() {
+        let svg = render(|ctx| {
+            let mut style = StrokeStyle::new();
+            style.set_dash(vec![3.0, 1.0, 2.0], 0.0);
+            ctx.stroke_styled(
+                Line::new((0.0, 0.5), (12.0, 0.5)),
+                &Color::BLACK,
+                1.0,
+                &style,
+            );
+        });
+        assert!(svg.contains(r#"stroke-dasharray="3 1 2 3 1 2""#), "{}", svg);
+    }
+
+    #[test]
+    fn stroke_dash_offset() {
+        let svg = render(|ctx| {
+            let mut style = StrokeStyle::new();
+            style.set_dash(vec![4.0, 2.0], 1.5);
+            ctx.stroke_styled(
+                Line::new((0.0, 0.0), (10.0, 0.0)),
+                &Color::BLACK,
+                1.0,
+                &style,
+            );
+            style.set_dash(vec![4.0, 2.0], 0.0);
+            ctx.stroke_styled(
+                Line::new((0.0, 0.0), (10.0, 0.0)),
+                &Color::BLACK,
+                1.0,
+                &style,
+            );
+        });
+        assert_eq!(svg.matches(r#"stroke-dashoffset="1.5""#).count(), 1);
+        assert_eq!(svg.matches("stroke-dashoffset").count(), 1);
+    }
+
+    #[test]
+    fn stroke_line_cap() {
+        let svg = render(|ctx| {
+            let mut style = StrokeStyle::new();
+            style.set_line_cap(LineCap::Round);
+            ctx.stroke_styled(
+                Line::new((0.0, 0.0), (10.0, 0.0)),
+                &Color::BLACK,
+                1.0,
+                &style,
+            );
+            style.set_line_cap(LineCap::Butt);
+            ctx.stroke_styled(
+                Line::new((0.0, 0.0), (10.0, 0.0)),
+                &Color::BLACK,
+                1.0,
+                &style,
+            );
+        });
+        assert_eq!(svg.matches(r#"stroke-linecap="round""#).count(), 1);
+        assert_eq!(svg.matches("stroke-linecap").count(), 1);
+    }
+
+    #[test]
+    fn stroke_line_join() {
+        let svg = render(|ctx| {
+            let mut path = BezPath::new();
+            path.move_to((0.0, 0.0));
+            path.line_to((10.0, 0.0));
+            path.line_to((0.0, 5.0));
+            let mut style = StrokeStyle::new();
+            style.set_line_join(LineJoin::Bevel);
+            ctx.stroke_styled(&path, &Color::BLACK, 1.0, &style);
+            style.set_line_join(LineJoin::Miter);
+            ctx.stroke_styled(&path, &Color::BLACK, 1.0, &style);
+        });
+        assert_eq!(svg.matches(r#"stroke-linejoin="bevel""#).count(), 1);
+        assert_eq!(svg.matches("stroke-linejoin").count(), 1);
+    }
+
+    #[test]
+    fn stroke_miter_limit() {
+        let line = Line::new((0.0, 0.0), (10.0, 0.0));
+        let svg = render(|ctx| {
+            let mut style = StrokeStyle::new();
+            ctx.stroke_styled(line, &Color::BLACK, 1.0, &style);
+            style.set_miter_limit(2.5);
+            ctx.stroke_styled(line, &Color::BLACK, 1.0, &style);
+            style.set_miter_limit(4.0);
+            ctx.stroke_styled(line, &Color::BLACK, 1.0, &style);
+            style.set_line_join(LineJoin::Round);
+            style.set_miter_limit(2.5);
+            ctx.stroke_styled(line, &Color::BLACK, 1.0, &style);
+        });
+        assert_eq!(svg.matches(r#"stroke-miterlimit="10""#).count(), 1);
+        assert_eq!(svg.matches(r#"stroke-miterlimit="2.5""#).count(), 1);
+        assert_eq!(svg.matches("stroke-miterlimit").count(), 2);
+    }
+
+    #[test]
+    fn opacity() {
+        let svg = render(|ctx| {
+            let rect = Rect::new(0.0, 0.0, 10.0, 10.0);
+            ctx.fill(rect, &Color::rgba(1.0, 0.0, 0.0, 0.5));
+            ctx.stroke(rect, &Color::rgba8(0, 0, 0xff, 0x40), 1.0);
+            ctx.clear(Color::rgba8(0, 0, 0, 1));
+        });
+        assert!(svg.contains(r##"fill="#ff0000" fill-opacity="0.5""##));
+        assert!(svg.contains(r##"stroke="#0000ff""##));
+        assert!(svg.contains(r#"stroke-opacity="0.25""#));
+        assert!(svg.contains(r##"fill="#000000" fill-opacity="0.004""##));
+    }
+
+    #[test]
+    fn fill_even_odd() {
+        let svg = render(|ctx| {
+            let outer = Rect::new(0.0, 0.0, 10.0, 10.0).to_bez_path(0.1);
+            let inner = Rect::new(2.0, 2.0, 8.0, 8.0).to_bez_path(0.1);
+            let path: BezPath = outer.chain(inner).collect();
+            ctx.fill_even_odd(&path, &Color::BLACK);
+            ctx.fill(&path, &Color::BLACK);
+        });
+        assert_eq!(svg.matches(r#"fill-rule="evenodd""#).count(), 1);
+        assert_eq!(svg.matches("fill-rule").count(), 1);
+        assert_eq!(svg.matches("<path").count(), 2);
+    }
+
+    #[test]
+    fn blend_mode() {
+        let svg = render(|ctx| {
+            ctx.save().unwrap();
+            ctx.set_blend_mode(BlendMode::Multiply);
+            ctx.fill(Rect::new(0.0, 0.0, 10.0, 10.0), &Color::BLACK);
+            ctx.restore().unwrap();
+            ctx.fill(Rect::new(0.0, 0.0, 10.0, 10.0), &Color::WHITE);
+        });
+        assert!(svg.contains(concat!(
+            "<g style=\"mix-blend-mode: multiply\">\n",
+            "<rect fill=\"#000000\" height=\"10\" width=\"10\" x=\"0\" y=\"0\"/>\n",
+            "</g>\n",
+            "<rect fill=\"#ffffff\"",
+        )));
+    }
+
+    #[test]
+    fn blend_mode_normal() {
+        let svg = render(|ctx| {
+            ctx.save().unwrap();
+            ctx.set_blend_mode(BlendMode::Multiply);
+            ctx.clip(Rect::new(0.0, 0.0, 5.0, 5.0));
+            ctx.fill(Rect::new(0.0, 0.0, 10.0, 10.0), &Color::BLACK);
+            ctx.set_blend_mode(BlendMode::Normal);
+            ctx.fill(Rect::new(0.0, 0.0, 10.0, 10.0), &Color::WHITE);
+            ctx.restore().unwrap();
+        });
+        // the clip still applies after the blend group
+        assert!(
+            svg.contains(concat!(
+                "<g style=\"mix-blend-mode: multiply\">\n",
+                "<g clip-path=\"url(#a)\">\n",
+                "<rect fill=\"#000000\" height=\"10\" width=\"10\" x=\"0\" y=\"0\"/>\n",
+                "</g>\n",
+                "</g>\n",
+                "<g clip-path=\"url(#a)\">\n",
+                "<rect fill=\"#ffffff\"",
+            )),
+            "{}",
+            svg
+        );
+    }
+
+    #[test]
+    fn precision() {
+        let mut path = BezPath::new();
+        path.move_to((1.0 / 3.0, 0.0));
+        path.line_to((2.0 / 3.0, -0.001));
+        let svg = render(|ctx| {
+            ctx.set_precision(Some(2));
+            ctx.transform(Affine::translate((1.0 / 3.0, 0.0)));
+            ctx.fill(&path, &Color::BLACK);
+            ctx.fill(Circle::new((0.0, 0.0), 2.0 / 3.0), &Color::BLACK);
+            let gradient = FixedLinearGradient {
+                start: Point::new(0.0, 0.0),
+                end: Point::new(2.0 / 3.0, 0.0),
+                stops: (Color::BLACK, Color::WHITE).to_vec(),
+                extend: GradientExtend::Pad,
+            };
+            ctx.gradient(gradient).unwrap();
+            ctx.set_precision(None);
+            ctx.fill(&path, &Color::BLACK);
+        });
+        assert!(svg.contains(r#"transform="matrix(1 0 0 1 0.33 0)""#));
+        assert!(svg.contains(r#"d="M0.33 0L0.67 0""#));
+        assert!(svg.contains(r#"r="0.67""#));
+        assert!(svg.contains(r#"x2="0.67""#));
+        assert!(svg.contains(r#"d="M0.3333333333333333 0L0.6666666666666666 -0.001""#));
+    }
+
+    #[test]
+    fn relative_paths() {
+        let mut path = BezPath::new();
+        path.move_to((100.0, 100.0));
+        path.line_to((110.0, 100.0));
+        path.line_to((110.0, 110.0));
+        path.curve_to((110.0, 120.0), (100.0, 120.0), (95.5, 110.0));
+        path.close_path();
+        path.move_to((100.0, 150.0));
+        path.line_to((90.0, 150.0));
+        let absolute = render(|ctx| ctx.fill(&path, &Color::BLACK));
+        let relative = render(|ctx| {
+            ctx.set_relative_paths(true);
+            ctx.fill(&path, &Color::BLACK);
+        });
+        assert!(absolute
+            .contains(r#"d="M100 100L110 100L110 110C110 120 100 120 95.5 110ZM100 150L90 150""#));
+        assert!(relative.contains(r#"d="m100 100l10 0 0 10c0 10-10 10-14.5 0zm0 50l-10 0""#));
+        assert!(relative.len() < absolute.len());
+    }
+
+    #[test]
+    fn fill_rect() {
+        let svg = render(|ctx| {
+            ctx.fill(Rect::new(1.0, 2.0, 11.0, 22.0), &Color::BLACK);
+        });
+        assert!(svg.contains(r##"<rect fill="#000000" height="20" width="10" x="1" y="2"/>"##));
+        assert!(!svg.contains("<path"));
+    }
+
+    #[test]
+    fn fill_rounded_rect() {
+        let svg = render(|ctx| {
+            let rect = Rect::new(0.0, 0.0, 10.0, 20.0);
+            ctx.fill(RoundedRect::from_rect(rect, 2.5), &Color::BLACK);
+            ctx.fill(RoundedRect::from_rect(rect, 0.0), &Color::BLACK);
+        });
+        assert!(svg.contains(r#"height="20" rx="2.5" ry="2.5" width="10" x="0" y="0"/>"#));
+        assert_eq!(svg.matches("<rect").count(), 2);
+        assert_eq!(svg.matches("rx=").count(), 1);
+    }
+
+    #[test]
+    fn fill_circle() {
+        let svg = render(|ctx| {
+            ctx.fill(Circle::new((5.0, 6.0), 4.0), &Color::BLACK);
+        });
+        assert!(svg.contains(r##"<circle cx="5" cy="6" fill="#000000" r="4"/>"##));
+    }
+
+    #[test]
+    fn stroke_line() {
+        let svg = render(|ctx| {
+            ctx.stroke(Line::new((1.0, 2.0), (3.0, 4.0)), &Color::BLACK, 2.0);
+        });
+        assert!(svg.contains(r##"<line fill="none" stroke="#000000""##));
+        assert!(svg.contains(r#"x1="1" x2="3" y1="2" y2="4"/>"#));
+        assert!(!svg.contains("<path"));
+    }
+
+    #[test]
+    fn blurred_rect() {
+        let svg = render(|ctx| {
+            ctx.blurred_rect(Rect::new(10.0, 10.0, 30.0, 20.0), 2.0, &Color::BLACK);
+        });
+        assert!(svg.contains(r#"<feGaussianBlur stdDeviation="2"/>"#));
+        assert!(svg.contains(r#"height="22" id="a" width="32" x="4" y="4""#));
+        assert!(svg.contains(r#"filter="url(#a)""#));
+    }
+
+    #[test]
+    fn image_pattern() {
+        let svg = render(|ctx| {
+            let image = ctx
+                .make_image(2, 2, &[0xff; 2 * 2 * 3], ImageFormat::Rgb)
+                .unwrap();
+            let brush = ctx.image_pattern(&image, Rect::new(5.0, 5.0, 9.0, 9.0));
+            ctx.fill(Rect::new(0.0, 0.0, 100.0, 100.0), &brush);
+        });
+        assert!(svg.contains(
+            r#"<pattern height="4" id="a" patternUnits="userSpaceOnUse" width="4" x="5" y="5">"#
+        ));
+        assert!(svg.contains(r#"href="data:image/png;base64,"#));
+        assert!(svg.contains(r#"fill="url(#a)""#));
+    }
+
+    #[test]
+    fn make_image_invalid() {
+        let mut ctx = RenderContext::new();
+        assert!(ctx.make_image(2, 2, &[0; 3], ImageFormat::Rgb).is_err());
+        // the byte count of these dimensions overflows
+        let huge = usize::MAX / 2;
+        assert!(ctx.make_image(huge, 3, &[0; 12], ImageFormat::Rgb).is_err());
+        assert!(ctx
+            .make_image(2, 2, &[0; 4], ImageFormat::Grayscale)
+            .is_err());
+    }
+
+    #[test]
+    fn draw_text() {
+        let svg = render_text("hi", 12.0, (10.0, 20.0));
+        assert!(svg.contains(r#"font-family="sans-serif""#));
+        assert!(svg.contains(r#"font-size="12""#));
+        assert!(svg.contains("<tspan x=\"10\" y=\"20\">\nhi\n</tspan>"));
+    }
+
+    #[test]
+    fn draw_text_multiline() {
+        let svg = render_text("one\ntwo", 10.0, (0.0, 10.0));
+        assert_eq!(svg.matches("<tspan").count(), 2);
+        assert!(svg.contains("<tspan x=\"0\" y=\"10\">\none\n</tspan>"));
+        assert!(svg.contains("<tspan x=\"0\" y=\"22\">\ntwo\n</tspan>"));
+    }
+
+    #[test]
+    fn draw_text_bold() {
+        let svg = render(|ctx| {
+            let font = ctx
+                .text()
+                .new_font_by_name("sans-serif", 10.0)
+                .with_weight(FontWeight::Bold)
+                .with_style(FontStyle::Italic)
+                .build()
+                .unwrap();
+            let layout = ctx
+                .text()
+                .new_text_layout(&font, "hi", None)
+                .build()
+                .unwrap();
+            ctx.draw_text(&layout, (0.0, 0.0), &Color::BLACK);
+        });
+        assert!(svg.contains(r#"font-weight="bold""#));
+        assert!(svg.contains(r#"font-style="italic""#));
+    }
+
+    #[test]
+    fn draw_text_fill() {
+        let svg = render(|ctx| {
+            let layout = text_layout(ctx, "hi", 10.0);
+            ctx.draw_text(&layout, (0.0, 0.0), &Color::rgb8(0xff, 0, 0));
+            ctx.draw_text(&layout, (0.0, 20.0), &Color::rgba8(0, 0, 0xff, 0x33));
+        });
+        assert!(svg.contains(r##"fill="#ff0000""##));
+        assert!(svg.contains(r##"fill="#0000ff" fill-opacity="0.2""##));
+        assert_eq!(svg.matches("fill-opacity").count(), 1);
+    }
+
+    #[test]
+    fn draw_text_embedded_font() {
+        let svg = render(|ctx| {
+            ctx.set_embed_fonts(true);
+            ctx.text()
+                .load_font_data("Embedded", &b"OTTO font data"[..]);
+            let font = ctx
+                .text()
+                .new_font_by_name("Embedded", 10.0)
+                .build()
+                .unwrap();
+            let layout = ctx
+                .text()
+                .new_text_layout(&font, "hi", None)
+                .build()
+                .unwrap();
+            ctx.draw_text(&layout, (0.0, 0.0), &Color::BLACK);
+            ctx.draw_text(&layout, (0.0, 20.0), &Color::BLACK);
+        });
+        assert_eq!(svg.matches("@font-face").count(), 1);
+        assert!(svg.contains(
+            "src: url(data:font/otf;base64,T1RUTyBmb250IGRhdGE=) format(&quot;opentype&quot;)"
+        ));
+    }
+
+    #[test]
+    fn draw_text_centered() {
+        let svg = render(|ctx| {
+            let font = ctx
+                .text()
+                .new_font_by_name("sans-serif", 10.0)
+                .build()
+                .unwrap();
+            let layout = ctx
+                .text()
+                .new_text_layout(&font, "hi", 100.0)
+                .with_alignment(TextAlignment::Center)
+                .build()
+                .unwrap();
+            ctx.draw_text(&layout, (10.0, 10.0), &Color::BLACK);
+        });
+        assert!(svg.contains(r#"text-anchor="middle""#));
+        assert!(svg.contains(r#"<tspan x="60" y="10">"#));
+    }
+
+    #[test]
+    fn draw_text_spaces() {
+        let svg = render_text("  a  b", 10.0, (0.0, 10.0));
+        assert!(
+            svg.contains("\n\u{a0}\u{a0}a\u{a0}\u{a0}b\n</tspan>"),
+            "{}",
+            svg
+        );
+    }
+
+    #[test]
+    fn draw_text_escaped() {
+        let svg = render_text("a<b&c", 12.0, (0.0, 0.0));
+        assert!(svg.contains("<text"));
+        assert!(svg.contains("a&lt;b&amp;c"));
+    }
+}
@@ -0,0 +1,261 @@
+//! Writing the document in the pretty and minified output styles.
+//!
+//! These work on the document as serialized by the svg crate, which puts a newline
+//! before every child node and before every closing tag, and which we never put a `<`
+//! or `>` into, other than as the delimiters of tags.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::mem;
+
+/// Elements whose content is text, in which whitespace is significant.
+const TEXT_ELEMENTS: &[&str] = &["desc", "style", "text", "title", "tspan"];
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Style {
+    /// Each element on its own line, indented by its depth.
+    ///
+    /// The content of text elements is written as is, so this renders the same as the
+    /// compact output.
+    Pretty,
+    /// No whitespace between elements, with shorter forms of colors and transforms, and
+    /// without attributes that have their default values.
+    ///
+    /// The content of text elements is written as is.
+    Minified,
+}
+
+/// Rewrites the document in one of the output styles as it's written to it.
+///
+/// Only the tag being written, or the whitespace between two tags, is held back;
+/// everything else is passed on to the underlying writer straight away. Call
+/// `finish` once the whole document has been written.
+pub(crate) struct Writer<W> {
+    out: W,
+    style: Style,
+    /// The tag being written, or the text since the last tag outside text elements.
+    pending: String,
+    in_tag: bool,
+    depth: usize,
+    /// The depth of elements within a text element, which are written as is.
+    text_depth: usize,
+}
+
+impl<W: fmt::Write> Writer<W> {
+    pub(crate) fn new(out: W, style: Style) -> Self {
+        Writer {
+            out,
+            style,
+            pending: String::new(),
+            in_tag: false,
+            depth: 0,
+            text_depth: 0,
+        }
+    }
+
+    /// Write out anything still held back.
+    pub(crate) fn finish(mut self) -> fmt::Result {
+        if self.in_tag {
+            self.in_tag = false;
+            let tag = mem::take(&mut self.pending);
+            self.write_tag(&tag)
+        } else {
+            self.write_pending_text()
+        }
+    }
+
+    /// Write the text between two tags outside text elements.
+    fn write_pending_text(&mut self) -> fmt::Result {
+        let text = mem::take(&mut self.pending);
+        // whitespace between elements
+        if text.trim().is_empty() {
+            return Ok(());
+        }
+        match self.style {
+            Style::Pretty => writeln!(self.out, "{:width$}{}", "", text, width = 2 * self.depth),
+            Style::Minified => self.out.write_str(&text),
+        }
+    }
+
+    fn write_tag(&mut self, tag: &str) -> fmt::Result {
+        let kind = tag_kind(tag);
+        match self.style {
+            Style::Pretty => self.write_pretty_tag(tag, kind),
+            Style::Minified => {
+                write_minified_tag(&mut self.out, tag, kind)?;
+                match kind {
+                    TagKind::Open(name) if self.text_depth > 0 || TEXT_ELEMENTS.contains(&name) => {
+                        self.text_depth += 1
+                    }
+                    TagKind::Close if self.text_depth > 0 => self.text_depth -= 1,
+                    _ => {}
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn write_pretty_tag(&mut self, tag: &str, kind: TagKind) -> fmt::Result {
+        if self.text_depth > 0 {
+            self.out.write_str(tag)?;
+            match kind {
+                TagKind::Open(_) => self.text_depth += 1,
+                TagKind::Close => {
+                    self.text_depth -= 1;
+                    if self.text_depth == 0 {
+                        writeln!(self.out)?;
+                    }
+                }
+                TagKind::Empty => {}
+            }
+            return Ok(());
+        }
+        if kind == TagKind::Close {
+            self.depth = self.depth.saturating_sub(1);
+        }
+        write!(self.out, "{:width$}{}", "", tag, width = 2 * self.depth)?;
+        match kind {
+            TagKind::Open(name) if TEXT_ELEMENTS.contains(&name) => self.text_depth = 1,
+            TagKind::Open(_) => {
+                self.depth += 1;
+                writeln!(self.out)?;
+            }
+            _ => writeln!(self.out)?,
+        }
+        Ok(())
+    }
+}
+
+impl<W: fmt::Write> fmt::Write for Writer<W> {
+    fn write_str(&mut self, mut s: &str) -> fmt::Result {
+        while !s.is_empty() {
+            if self.in_tag {
+                match s.find('>') {
+                    Some(end) => {
+                        self.pending.push_str(&s[..=end]);
+                        s = &s[end + 1..];
+                        self.in_tag = false;
+                        let tag = mem::take(&mut self.pending);
+                        self.write_tag(&tag)?;
+                    }
+                    None => {
+                        self.pending.push_str(s);
+                        s = "";
+                    }
+                }
+                continue;
+            }
+            let end = s.find('<').unwrap_or(s.len());
+            if self.text_depth > 0 {
+                self.out.write_str(&s[..end])?;
+            } else {
+                self.pending.push_str(&s[..end]);
+            }
+            s = &s[end..];
+            if !s.is_empty() {
+                self.write_pending_text()?;
+                self.in_tag = true;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_minified_tag(f: &mut impl fmt::Write, tag: &str, kind: TagKind) -> fmt::Result {
+    if kind == TagKind::Close {
+        return f.write_str(tag);
+    }
+    let end = if kind == TagKind::Empty { "/>" } else { ">" };
+    let tag = &tag[1..tag.len() - end.len()];
+    let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+    let (element, mut attrs) = tag.split_at(name_end);
+    write!(f, "<{}", element)?;
+    while let Some(eq) = attrs.find('=') {
+        let name = attrs[..eq].trim();
+        let quote = attrs[eq + 1..].chars().next().unwrap_or('"');
+        let value_start = eq + 1 + quote.len_utf8();
+        let value_end = attrs[value_start..]
+            .find(quote)
+            .map_or(attrs.len(), |len| value_start + len);
+        let value = &attrs[value_start..value_end];
+        attrs = attrs.get(value_end + 1..).unwrap_or("");
+        if is_default(element, name, value) {
+            continue;
+        }
+        let value = shorten(name, value);
+        let quote = if value.contains('"') { '\'' } else { '"' };
+        write!(f, " {}={}{}{}", name, quote, value, quote)?;
+    }
+    f.write_str(end)
+}
+
+/// Whether the attribute has its initial value, and isn't inherited, so that leaving
+/// it out doesn't change the document.
+fn is_default(element: &str, name: &str, value: &str) -> bool {
+    match name {
+        "x" | "y" => value == "0" && ["image", "pattern", "rect", "svg", "use"].contains(&element),
+        "offset" => value == "0" && element == "stop",
+        "opacity" | "stop-opacity" => value == "1",
+        "filterUnits" | "gradientUnits" | "patternUnits" => value == "objectBoundingBox",
+        "preserveAspectRatio" => value == "xMidYMid meet",
+        "spreadMethod" => value == "pad",
+        _ => false,
+    }
+}
+
+/// A shorter form of the attribute value, if it has one.
+fn shorten<'a>(name: &str, value: &'a str) -> Cow<'a, str> {
+    let bytes = value.as_bytes();
+    if bytes.len() == 7
+        && bytes[0] == b'#'
+        && bytes[1..].iter().all(u8::is_ascii_hexdigit)
+        && bytes[1] == bytes[2]
+        && bytes[3] == bytes[4]
+        && bytes[5] == bytes[6]
+    {
+        let (r, g, b) = (bytes[1] as char, bytes[3] as char, bytes[5] as char);
+        return format!("#{}{}{}", r, g, b).into();
+    }
+    if name.ends_with("transform") || name.ends_with("Transform") {
+        if let Some(short) = shorten_matrix(value) {
+            return short.into();
+        }
+    }
+    value.into()
+}
+
+/// Write a `matrix(..)` transform that only translates or only scales as such.
+fn shorten_matrix(value: &str) -> Option<String> {
+    let coeffs = value.strip_prefix("matrix(")?.strip_suffix(')')?;
+    let coeffs: Vec<&str> = coeffs.split_whitespace().collect();
+    match coeffs[..] {
+        ["1", "0", "0", "1", x, "0"] => Some(format!("translate({})", x)),
+        ["1", "0", "0", "1", x, y] => Some(format!("translate({} {})", x, y)),
+        [x, "0", "0", y, "0", "0"] if x == y => Some(format!("scale({})", x)),
+        [x, "0", "0", y, "0", "0"] => Some(format!("scale({} {})", x, y)),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum TagKind<'a> {
+    /// An opening tag, with the name of its element.
+    Open(&'a str),
+    Close,
+    /// The tag of an element without content.
+    Empty,
+}
+
+/// The kind of a serialized tag.
+fn tag_kind(tag: &str) -> TagKind<'_> {
+    if tag.starts_with("</") {
+        TagKind::Close
+    } else if tag.ends_with("/>") {
+        TagKind::Empty
+    } else {
+        let name_end = tag
+            .find(|c: char| c.is_whitespace() || c == '>')
+            .unwrap_or(tag.len());
+        TagKind::Open(&tag[1..name_end])
+    }
+}
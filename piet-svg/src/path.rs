@@ -0,0 +1,95 @@
+//! Writing path data.
+
+use std::fmt::Write;
+
+use piet::kurbo::{BezPath, PathEl, Point};
+
+use crate::{round, Format};
+
+/// Format a path as the value of an SVG `d` attribute.
+///
+/// In relative mode, points are written as offsets from the current point, command
+/// letters that would repeat are omitted, and so is the separator before a negative
+/// number.
+///
+/// Path data must start with a move, so if the path doesn't, its first subpath starts at
+/// the origin.
+pub(crate) fn path_data(path: &BezPath, format: Format) -> String {
+    let mut writer = PathWriter {
+        out: String::new(),
+        format,
+        last_cmd: None,
+        current: Point::ZERO,
+        start: Point::ZERO,
+    };
+    for el in path.elements() {
+        if writer.last_cmd.is_none() && !matches!(el, PathEl::MoveTo(_)) {
+            writer.command('M', &[Point::ZERO]);
+        }
+        match *el {
+            PathEl::MoveTo(p) => {
+                writer.command('M', &[p]);
+                writer.start = writer.current;
+            }
+            PathEl::LineTo(p) => writer.command('L', &[p]),
+            PathEl::QuadTo(p1, p2) => writer.command('Q', &[p1, p2]),
+            PathEl::CurveTo(p1, p2, p3) => writer.command('C', &[p1, p2, p3]),
+            PathEl::ClosePath => {
+                writer.command('Z', &[]);
+                writer.current = writer.start;
+            }
+        }
+    }
+    writer.out
+}
+
+struct PathWriter {
+    out: String,
+    format: Format,
+    last_cmd: Option<char>,
+    /// The current point, after rounding.
+    current: Point,
+    /// The start of the current subpath, after rounding.
+    start: Point,
+}
+
+impl PathWriter {
+    fn command(&mut self, cmd: char, points: &[Point]) {
+        let relative = self.format.relative_paths;
+        let cmd = if relative {
+            cmd.to_ascii_lowercase()
+        } else {
+            cmd
+        };
+        // a command following itself can be implied, except for a move, which is followed
+        // by implicit line commands
+        let implied = relative && self.last_cmd == Some(cmd) && cmd != 'm' && cmd != 'z';
+        if !implied {
+            self.out.push(cmd);
+        }
+        let origin = self.current.to_vec2();
+        for (i, p) in points.iter().enumerate() {
+            let p = Point::new(
+                round(p.x, self.format.precision),
+                round(p.y, self.format.precision),
+            );
+            let v = if relative { p - origin } else { p };
+            let first = i == 0 && !implied;
+            self.number(v.x, first);
+            self.number(v.y, false);
+            self.current = p;
+        }
+        self.last_cmd = Some(cmd);
+    }
+
+    fn number(&mut self, x: f64, first: bool) {
+        // rounding again removes the error from taking the difference of rounded numbers
+        let x = round(x, self.format.precision);
+        // a minus sign also separates numbers
+        let separated = first || (self.format.relative_paths && x < 0.0);
+        if !separated {
+            self.out.push(' ');
+        }
+        write!(self.out, "{}", x).unwrap();
+    }
+}
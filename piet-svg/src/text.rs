@@ -1,17 +1,50 @@
 //! Text functionality for Piet svg backend
 
+mod lines;
+mod metrics;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
 use piet::kurbo::Point;
-use piet::{new_error, Error, ErrorKind, HitTestPoint, HitTestTextPosition, LineMetric};
+use piet::{Error, HitTestMetrics, HitTestPoint, HitTestTextPosition, LineMetric};
+use unicode_segmentation::UnicodeSegmentation;
 
 type Result<T> = std::result::Result<T, Error>;
 
-/// SVG text (unimplemented)
-pub struct Text(());
+/// SVG text
+///
+/// Text is measured with a bundled table of approximate font metrics, as the
+/// fonts used to render the document are chosen by the viewer.
+pub struct Text {
+    font_data: HashMap<String, Rc<[u8]>>,
+}
 
 impl Text {
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
-        Text(())
+        Text {
+            font_data: HashMap::new(),
+        }
+    }
+
+    /// Provide the contents of a font file (TrueType, OpenType, or WOFF) for a font family.
+    ///
+    /// Fonts subsequently created with this family name can be embedded in the
+    /// document, see [`RenderContext::set_embed_fonts`].
+    ///
+    /// [`RenderContext::set_embed_fonts`]: struct.RenderContext.html#method.set_embed_fonts
+    pub fn load_font_data(&mut self, family: &str, data: impl Into<Vec<u8>>) {
+        self.font_data.insert(family.to_owned(), data.into().into());
+    }
+}
+
+impl fmt::Debug for Text {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Text")
+            .field("font_data", &self.font_data.keys().collect::<Vec<_>>())
+            .finish()
     }
 }
 
@@ -21,80 +54,573 @@ impl piet::Text for Text {
     type TextLayout = TextLayout;
     type TextLayoutBuilder = TextLayoutBuilder;
 
-    fn new_font_by_name(&mut self, _name: &str, _size: f64) -> FontBuilder {
-        FontBuilder(())
+    fn new_font_by_name(&mut self, name: &str, size: f64) -> FontBuilder {
+        FontBuilder(Font {
+            family: name.to_owned(),
+            data: self.font_data.get(name).cloned(),
+            size,
+            weight: FontWeight::Normal,
+            style: FontStyle::Normal,
+        })
     }
 
     fn new_text_layout(
         &mut self,
-        _font: &Self::Font,
-        _text: &str,
-        _width: impl Into<Option<f64>>,
+        font: &Self::Font,
+        text: &str,
+        width: impl Into<Option<f64>>,
     ) -> TextLayoutBuilder {
-        TextLayoutBuilder(())
+        let mut text_layout = TextLayout {
+            font: font.clone(),
+            text: text.to_owned(),
+            alignment: TextAlignment::Start,
+            wrap_width: f64::INFINITY,
+            line_metrics: Vec::new(),
+            width: 0.0,
+        };
+        text_layout.layout_lines(width.into());
+        TextLayoutBuilder(text_layout)
     }
 }
 
-/// SVG font builder (unimplemented)
-pub struct FontBuilder(());
+/// SVG font builder
+#[derive(Debug)]
+pub struct FontBuilder(Font);
+
+/// The weight of a font.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FontWeight {
+    Normal,
+    Bold,
+}
+
+/// The style of a font.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FontStyle {
+    Normal,
+    Italic,
+    Oblique,
+}
+
+impl FontBuilder {
+    /// A builder-style method for changing the weight of the font.
+    ///
+    /// By default, fonts have a normal weight.
+    pub fn with_weight(mut self, weight: FontWeight) -> Self {
+        self.0.weight = weight;
+        self
+    }
+
+    /// A builder-style method for changing the style of the font.
+    ///
+    /// By default, fonts have a normal style.
+    pub fn with_style(mut self, style: FontStyle) -> Self {
+        self.0.style = style;
+        self
+    }
+}
 
 impl piet::FontBuilder for FontBuilder {
     type Out = Font;
 
     fn build(self) -> Result<Font> {
-        Err(new_error(ErrorKind::NotSupported))
+        Ok(self.0)
     }
 }
 
-/// SVG font (unimplemented)
-pub struct Font(());
+/// SVG font
+#[derive(Clone)]
+pub struct Font {
+    pub(crate) family: String,
+    /// The contents of the font file, if it was provided.
+    pub(crate) data: Option<Rc<[u8]>>,
+    pub(crate) size: f64,
+    pub(crate) weight: FontWeight,
+    pub(crate) style: FontStyle,
+}
 
 impl piet::Font for Font {}
 
-pub struct TextLayoutBuilder(());
+impl fmt::Debug for Font {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // the font file is summarized by its size
+        f.debug_struct("Font")
+            .field("family", &self.family)
+            .field("data", &self.data.as_ref().map(|data| data.len()))
+            .field("size", &self.size)
+            .field("weight", &self.weight)
+            .field("style", &self.style)
+            .finish()
+    }
+}
+
+impl Font {
+    /// The advance width of `text` in this font, ignoring any line breaks.
+    fn text_width(&self, text: &str) -> f64 {
+        UnicodeSegmentation::graphemes(text, true)
+            .map(metrics::grapheme_advance)
+            .sum::<f64>()
+            * self.size
+    }
+
+    fn line_height(&self) -> f64 {
+        self.size * 1.2
+    }
+
+    /// Distance from the top of a line to its baseline, centering the glyph
+    /// extents within the line height.
+    fn baseline(&self) -> f64 {
+        let extent = (metrics::ASCENT + metrics::DESCENT) * self.size;
+        metrics::ASCENT * self.size + (self.line_height() - extent) / 2.0
+    }
+}
+
+#[derive(Debug)]
+pub struct TextLayoutBuilder(TextLayout);
+
+/// Horizontal alignment of the lines in a text layout.
+///
+/// Lines are aligned within the layout's wrapping width, or within the width
+/// of its widest line if it isn't wrapped. This maps to the `text-anchor` of
+/// the emitted text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextAlignment {
+    /// Lines start at the left edge (`text-anchor="start"`).
+    Start,
+    /// Lines are centered (`text-anchor="middle"`).
+    Center,
+    /// Lines end at the right edge (`text-anchor="end"`).
+    End,
+}
+
+impl TextLayoutBuilder {
+    /// A builder-style method for changing the alignment of the layout's lines.
+    ///
+    /// By default, text is aligned to the start of the line.
+    pub fn with_alignment(mut self, alignment: TextAlignment) -> Self {
+        self.0.alignment = alignment;
+        self
+    }
+}
 
 impl piet::TextLayoutBuilder for TextLayoutBuilder {
     type Out = TextLayout;
 
     fn build(self) -> Result<TextLayout> {
-        Err(new_error(ErrorKind::NotSupported))
+        Ok(self.0)
     }
 }
 
-/// SVG text layout (unimplemented)
-#[derive(Clone)]
-pub struct TextLayout(());
+/// SVG text layout
+#[derive(Clone, Debug)]
+pub struct TextLayout {
+    pub(crate) font: Font,
+    pub(crate) text: String,
+    pub(crate) alignment: TextAlignment,
+    wrap_width: f64,
+
+    // calculated on build
+    pub(crate) line_metrics: Vec<LineMetric>,
+    pub(crate) width: f64,
+}
+
+impl TextLayout {
+    /// Break the text into lines for the given width, and measure the result.
+    fn layout_lines(&mut self, width: Option<f64>) {
+        self.wrap_width = width.unwrap_or(f64::INFINITY);
+        self.line_metrics = lines::calculate_line_metrics(&self.text, &self.font, self.wrap_width);
+        self.width = self
+            .line_metrics
+            .iter()
+            .map(|lm| {
+                self.font
+                    .text_width(&self.text[lm.start_offset..lm.end_offset])
+            })
+            .fold(0.0, f64::max);
+    }
+
+    /// The width that lines are aligned within.
+    pub(crate) fn alignment_width(&self) -> f64 {
+        if self.wrap_width.is_finite() {
+            self.wrap_width
+        } else {
+            self.width
+        }
+    }
+
+    /// The x offset of the text anchor of each line, for the layout's alignment.
+    pub(crate) fn anchor_offset(&self) -> f64 {
+        match self.alignment {
+            TextAlignment::Start => 0.0,
+            TextAlignment::Center => self.alignment_width() / 2.0,
+            TextAlignment::End => self.alignment_width(),
+        }
+    }
+
+    /// The x offset of the start of a line, for the layout's alignment.
+    fn line_offset(&self, lm: &LineMetric) -> f64 {
+        let line = &self.text[lm.start_offset..lm.end_offset - lm.trailing_whitespace];
+        let slack = self.alignment_width() - self.font.text_width(line);
+        match self.alignment {
+            TextAlignment::Start => 0.0,
+            TextAlignment::Center => slack / 2.0,
+            TextAlignment::End => slack,
+        }
+    }
+}
 
 impl piet::TextLayout for TextLayout {
     fn width(&self) -> f64 {
-        unimplemented!()
+        self.width
     }
 
-    #[allow(clippy::unimplemented)]
-    fn update_width(&mut self, _new_width: impl Into<Option<f64>>) -> Result<()> {
-        unimplemented!();
+    fn update_width(&mut self, new_width: impl Into<Option<f64>>) -> Result<()> {
+        self.layout_lines(new_width.into());
+        Ok(())
     }
 
-    #[allow(clippy::unimplemented)]
-    fn line_text(&self, _line_number: usize) -> Option<&str> {
-        unimplemented!();
+    fn line_text(&self, line_number: usize) -> Option<&str> {
+        self.line_metrics
+            .get(line_number)
+            .map(|lm| &self.text[lm.start_offset..lm.end_offset])
     }
 
-    #[allow(clippy::unimplemented)]
-    fn line_metric(&self, _line_number: usize) -> Option<LineMetric> {
-        unimplemented!();
+    fn line_metric(&self, line_number: usize) -> Option<LineMetric> {
+        self.line_metrics.get(line_number).cloned()
     }
 
-    #[allow(clippy::unimplemented)]
     fn line_count(&self) -> usize {
-        unimplemented!();
+        self.line_metrics.len()
+    }
+
+    fn hit_test_point(&self, point: Point) -> HitTestPoint {
+        // null case
+        if self.text.is_empty() {
+            return HitTestPoint::default();
+        }
+
+        // All lines share the same metrics. Coordinates have 0.0 at the first baseline.
+        let first_baseline = self.line_metrics.first().map(|l| l.baseline).unwrap_or(0.0);
+
+        let mut is_y_inside = point.y >= -first_baseline;
+        let lm = match self
+            .line_metrics
+            .iter()
+            .find(|l| l.cumulative_height - first_baseline >= point.y)
+        {
+            Some(lm) => lm,
+            None => {
+                // below the last line, so hit test against the last line
+                is_y_inside = false;
+                match self.line_metrics.last() {
+                    Some(lm) => lm,
+                    None => return HitTestPoint::default(),
+                }
+            }
+        };
+
+        let line = &self.text[lm.start_offset..lm.end_offset];
+        let mut htp = hit_test_line_point(&self.font, line, point.x - self.line_offset(lm));
+        htp.metrics.text_position += lm.start_offset;
+        htp.metrics.is_trailing_whitespace =
+            htp.metrics.text_position > lm.end_offset - lm.trailing_whitespace;
+        htp.is_inside &= is_y_inside;
+        htp
+    }
+
+    fn hit_test_text_position(&self, text_position: usize) -> Option<HitTestTextPosition> {
+        // positions past the end are treated as the end of the text
+        let text_position = text_position.min(self.text.len());
+
+        // the line is the last one starting at or before the text position
+        let (line_number, lm) = match self
+            .line_metrics
+            .iter()
+            .enumerate()
+            .take_while(|(_, l)| l.start_offset <= text_position)
+            .last()
+        {
+            Some(line) => line,
+            None => return Some(HitTestTextPosition::default()),
+        };
+
+        // We're counting the first line baseline as 0, and measuring to each line's baseline.
+        let y = line_number as f64 * lm.height;
+
+        let line = &self.text[lm.start_offset..lm.end_offset];
+        let x = line_position_x(&self.font, line, text_position - lm.start_offset)
+            + self.line_offset(lm);
+
+        Some(HitTestTextPosition {
+            point: Point::new(x, y),
+            metrics: HitTestMetrics {
+                text_position,
+                is_trailing_whitespace: text_position > lm.end_offset - lm.trailing_whitespace,
+            },
+        })
+    }
+}
+
+/// Hit test a single line of text, walking the grapheme advances until `x` is reached.
+///
+/// The returned text position is the grapheme boundary nearest to `x`.
+fn hit_test_line_point(font: &Font, line: &str, x: f64) -> HitTestPoint {
+    let mut htp = HitTestPoint::default();
+    if x <= 0.0 {
+        return htp;
+    }
+
+    let mut leading = 0.0;
+    for (idx, grapheme) in UnicodeSegmentation::grapheme_indices(line, true) {
+        let trailing = leading + metrics::grapheme_advance(grapheme) * font.size;
+        if x <= trailing {
+            let midpoint = leading + (trailing - leading) / 2.0;
+            htp.metrics.text_position = if x >= midpoint {
+                idx + grapheme.len()
+            } else {
+                idx
+            };
+            htp.is_inside = true;
+            return htp;
+        }
+        leading = trailing;
+    }
+
+    // past the end of the line
+    htp.metrics.text_position = line.len();
+    htp
+}
+
+/// The x offset of the leading edge of the grapheme containing `line_position`.
+fn line_position_x(font: &Font, line: &str, line_position: usize) -> f64 {
+    UnicodeSegmentation::grapheme_indices(line, true)
+        .take_while(|(idx, grapheme)| idx + grapheme.len() <= line_position)
+        .map(|(_, grapheme)| metrics::grapheme_advance(grapheme))
+        .sum::<f64>()
+        * font.size
+}
+
+#[cfg(test)]
+mod test {
+    use piet::kurbo::Point;
+    use piet::{FontBuilder, Text as _, TextLayout as _, TextLayoutBuilder};
+
+    use super::*;
+
+    fn layout(text: &str) -> TextLayout {
+        let mut factory = Text::new();
+        let font = factory
+            .new_font_by_name("sans-serif", 12.0)
+            .build()
+            .unwrap();
+        factory.new_text_layout(&font, text, None).build().unwrap()
+    }
+
+    #[test]
+    fn test_width() {
+        // Helvetica advances of p, i, e, t, in 1/1000 em
+        let expected = (556.0 + 222.0 + 556.0 + 278.0) / 1000.0 * 12.0;
+        assert!((layout("piet").width() - expected).abs() < 1e-9);
+        assert_eq!(layout("").width(), 0.0);
+    }
+
+    #[test]
+    fn test_hit_test_point_basic() {
+        let layout = layout("piet text!");
+        let width = layout.width();
+
+        // near the end, but still within the last grapheme
+        let pt = layout.hit_test_point(Point::new(width - 0.5, 0.0));
+        assert_eq!(pt.metrics.text_position, 10);
+        assert!(pt.is_inside);
+
+        // just inside the start
+        let pt = layout.hit_test_point(Point::new(0.5, 0.0));
+        assert_eq!(pt.metrics.text_position, 0);
+        assert!(pt.is_inside);
+
+        // past either end
+        let pt = layout.hit_test_point(Point::new(width + 10.0, 0.0));
+        assert_eq!(pt.metrics.text_position, 10);
+        assert!(!pt.is_inside);
+        let pt = layout.hit_test_point(Point::new(-10.0, 0.0));
+        assert_eq!(pt.metrics.text_position, 0);
+        assert!(!pt.is_inside);
+    }
+
+    #[test]
+    fn test_hit_test_point_complex() {
+        // "é" as e + combining acute accent is a single grapheme of 3 bytes
+        let layout = layout("e\u{301}a");
+        let e_width = layout.font.text_width("e");
+
+        let pt = layout.hit_test_point(Point::new(e_width * 0.75, 0.0));
+        assert_eq!(pt.metrics.text_position, 3);
+        let pt = layout.hit_test_point(Point::new(e_width * 0.25, 0.0));
+        assert_eq!(pt.metrics.text_position, 0);
+    }
+
+    #[test]
+    fn test_multiline_hit_test_point() {
+        let layout = layout("piet\ntext");
+        assert_eq!(layout.line_count(), 2);
+        let height = layout.line_metric(0).unwrap().height;
+
+        // start of the second line
+        let pt = layout.hit_test_point(Point::new(0.5, height));
+        assert_eq!(pt.metrics.text_position, 5);
+        assert!(pt.is_inside);
+
+        // below the last line
+        let pt = layout.hit_test_point(Point::new(0.5, height * 3.0));
+        assert_eq!(pt.metrics.text_position, 5);
+        assert!(!pt.is_inside);
+
+        // above the first line
+        let pt = layout.hit_test_point(Point::new(0.5, -height));
+        assert_eq!(pt.metrics.text_position, 0);
+        assert!(!pt.is_inside);
+    }
+
+    #[test]
+    fn test_hit_test_trailing_whitespace() {
+        let layout = layout("hi ");
+        let hi_width = layout.font.text_width("hi");
+
+        // past the last visible character
+        let pt = layout.hit_test_point(Point::new(layout.width() + 10.0, 0.0));
+        assert_eq!(pt.metrics.text_position, 3);
+        assert!(pt.metrics.is_trailing_whitespace);
+
+        // on the last visible character
+        let pt = layout.hit_test_point(Point::new(hi_width - 0.5, 0.0));
+        assert_eq!(pt.metrics.text_position, 2);
+        assert!(!pt.metrics.is_trailing_whitespace);
+
+        let pos = layout.hit_test_text_position(3).unwrap();
+        assert!(pos.metrics.is_trailing_whitespace);
+        let pos = layout.hit_test_text_position(1).unwrap();
+        assert!(!pos.metrics.is_trailing_whitespace);
+    }
+
+    #[test]
+    fn test_hit_test_text_position_basic() {
+        let layout = layout("piet text!");
+
+        let pos = layout.hit_test_text_position(0).unwrap();
+        assert_eq!(pos.point, Point::ZERO);
+        assert_eq!(pos.metrics.text_position, 0);
+
+        let pos = layout.hit_test_text_position(4).unwrap();
+        assert_eq!(pos.point.x, layout.font.text_width("piet"));
+
+        // past the end clamps
+        let pos = layout.hit_test_text_position(100).unwrap();
+        assert_eq!(pos.point.x, layout.width());
+        assert_eq!(pos.metrics.text_position, 10);
     }
 
-    fn hit_test_point(&self, _point: Point) -> HitTestPoint {
-        unimplemented!()
+    #[test]
+    fn test_hit_test_text_position_complex() {
+        // inside a grapheme, the leading edge of that grapheme is used
+        let layout = layout("ae\u{301}b");
+        let a_width = layout.font.text_width("a");
+        let pos = layout.hit_test_text_position(2).unwrap();
+        assert_eq!(pos.point.x, a_width);
+        assert_eq!(pos.metrics.text_position, 2);
     }
 
-    fn hit_test_text_position(&self, _text_position: usize) -> Option<HitTestTextPosition> {
-        unimplemented!()
+    #[test]
+    fn test_hit_test_round_trip() {
+        let layout = layout("piet text!\nsecond line");
+        for text_position in &[0, 3, 10, 11, 15, 22] {
+            let pos = layout.hit_test_text_position(*text_position).unwrap();
+            let pt = layout.hit_test_point(pos.point);
+            assert_eq!(pt.metrics.text_position, *text_position);
+        }
+    }
+
+    #[test]
+    fn test_hit_test_midpoint() {
+        let layout = layout("ab");
+        assert_eq!(layout.line_count(), 1);
+        let lm = layout.line_metric(0).unwrap();
+        assert!(lm.baseline > 0.0 && lm.baseline < lm.height);
+
+        let pt = layout.hit_test_point(Point::new(layout.font.text_width("a"), 0.0));
+        assert_eq!(pt.metrics.text_position, 1);
+        let pos = layout.hit_test_text_position(1).unwrap();
+        assert_eq!(pos.point.x, layout.font.text_width("a"));
+    }
+
+    #[test]
+    fn test_update_width() {
+        let mut layout = layout("piet text is wrapped");
+        assert_eq!(layout.line_count(), 1);
+        let full_width = layout.width();
+
+        let wrap_width = layout.font.text_width("piet text is");
+        layout.update_width(wrap_width + 1.0).unwrap();
+        assert_eq!(layout.line_count(), 2);
+        assert_eq!(layout.line_text(0), Some("piet text is "));
+        assert_eq!(layout.line_text(1), Some("wrapped"));
+        assert!(layout.width() < full_width);
+
+        // narrower than any word; each word gets its own line
+        layout.update_width(1.0).unwrap();
+        assert_eq!(layout.line_count(), 4);
+        assert_eq!(layout.line_metric(3).unwrap().start_offset, 13);
+        assert_eq!(layout.line_metric(0).unwrap().trailing_whitespace, 1);
+
+        layout.update_width(None).unwrap();
+        assert_eq!(layout.line_count(), 1);
+        assert_eq!(layout.width(), full_width);
+    }
+
+    #[test]
+    fn test_wrap_word_boundaries() {
+        let mut factory = Text::new();
+        let font = factory
+            .new_font_by_name("sans-serif", 12.0)
+            .build()
+            .unwrap();
+        // too narrow for "a well-known", which can be broken after the hyphen
+        let wrap_width = font.text_width("a well-known") - 1.0;
+        let layout = factory
+            .new_text_layout(&font, "a well-known piet text layout", wrap_width)
+            .build()
+            .unwrap();
+        let lines = (0..layout.line_count())
+            .map(|line| layout.line_text(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(lines, ["a well-", "known piet ", "text layout"]);
+        for line in lines {
+            assert!(layout.font.text_width(line.trim_end()) <= wrap_width);
+        }
+    }
+
+    #[test]
+    fn test_alignment() {
+        let mut factory = Text::new();
+        let font = factory
+            .new_font_by_name("sans-serif", 12.0)
+            .build()
+            .unwrap();
+        let layout = factory
+            .new_text_layout(&font, "piet text", 200.0)
+            .with_alignment(TextAlignment::Center)
+            .build()
+            .unwrap();
+        assert_eq!(layout.anchor_offset(), 100.0);
+
+        // the line is centered within the wrap width, so hit testing is too
+        let start = (200.0 - layout.width()) / 2.0;
+        let pos = layout.hit_test_text_position(0).unwrap();
+        assert_eq!(pos.point.x, start);
+        let pt = layout.hit_test_point(Point::new(start + 0.5, 0.0));
+        assert_eq!(pt.metrics.text_position, 0);
+        assert!(pt.is_inside);
+        let pt = layout.hit_test_point(Point::new(0.5, 0.0));
+        assert!(!pt.is_inside);
     }
 }
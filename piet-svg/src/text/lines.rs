@@ -0,0 +1,84 @@
+use xi_unicode::LineBreakIterator;
+
+use super::{Font, LineMetric};
+
+/// Break `text` into lines no wider than `width`.
+///
+/// Lines are broken greedily at word boundaries, and always at hard breaks. A single
+/// word that is wider than `width` gets a line of its own, and overflows it.
+pub(crate) fn calculate_line_metrics(text: &str, font: &Font, width: f64) -> Vec<LineMetric> {
+    let mut line_metrics = Vec::new();
+    let mut line_start = 0;
+    let mut prev_break = 0;
+    let mut cumulative_height = 0.0;
+
+    // vertical measures are constant across all lines, as we only have one set of metrics
+    let height = font.line_height();
+    let baseline = font.baseline();
+
+    for (line_break, is_hard_break) in LineBreakIterator::new(text) {
+        // trailing whitespace hangs past the end of the line, so it doesn't count
+        let curr_width = font.text_width(text[line_start..line_break].trim_end());
+        if curr_width > width && prev_break > line_start {
+            add_line_metric(
+                text,
+                line_start,
+                prev_break,
+                baseline,
+                height,
+                &mut cumulative_height,
+                &mut line_metrics,
+            );
+            line_start = prev_break;
+        }
+
+        if is_hard_break {
+            add_line_metric(
+                text,
+                line_start,
+                line_break,
+                baseline,
+                height,
+                &mut cumulative_height,
+                &mut line_metrics,
+            );
+            line_start = line_break;
+        }
+        prev_break = line_break;
+    }
+
+    line_metrics
+}
+
+fn add_line_metric(
+    text: &str,
+    start_offset: usize,
+    end_offset: usize,
+    baseline: f64,
+    height: f64,
+    cumulative_height: &mut f64,
+    line_metrics: &mut Vec<LineMetric>,
+) {
+    *cumulative_height += height;
+
+    let line = &text[start_offset..end_offset];
+    let trailing_whitespace = count_trailing_whitespace(line);
+
+    let line_metric = LineMetric {
+        start_offset,
+        end_offset,
+        trailing_whitespace,
+        baseline,
+        height,
+        cumulative_height: *cumulative_height,
+    };
+    line_metrics.push(line_metric);
+}
+
+fn count_trailing_whitespace(line: &str) -> usize {
+    line.chars()
+        .rev()
+        .take_while(|c| c.is_whitespace())
+        .map(char::len_utf8)
+        .sum()
+}
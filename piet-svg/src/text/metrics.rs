@@ -0,0 +1,54 @@
+//! Approximate glyph metrics for SVG text.
+//!
+//! The SVG backend doesn't have access to the fonts that will eventually be
+//! used to render the document, so measurement is done with a bundled table
+//! of advance widths. These are the widths of Helvetica (from the standard
+//! Adobe font metrics), which are a reasonable stand-in for most sans-serif
+//! fonts a viewer is likely to substitute.
+
+/// Advance widths for printable ASCII (0x20..=0x7e), in 1/1000 em.
+#[rustfmt::skip]
+const ASCII_ADVANCES: [u16; 95] = [
+    // space ! " # $ % & ' ( ) * + , - . /
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    // 0 - 9
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556,
+    // : ; < = > ? @
+    278, 278, 584, 584, 584, 556, 1015,
+    // A - Z
+    667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833,
+    722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611,
+    // [ \ ] ^ _ `
+    278, 278, 278, 469, 556, 333,
+    // a - z
+    556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833,
+    556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500,
+    // { | } ~
+    334, 260, 334, 584,
+];
+
+/// Advance used for characters not covered by the table.
+const DEFAULT_ADVANCE: u16 = 556;
+
+/// Ascent of the font, as a fraction of the font size.
+pub(crate) const ASCENT: f64 = 0.718;
+
+/// Descent of the font (below the baseline), as a fraction of the font size.
+pub(crate) const DESCENT: f64 = 0.207;
+
+/// The advance width of a grapheme cluster, in em units.
+///
+/// Only the first character of the cluster contributes, so combining marks
+/// don't add to the width.
+pub(crate) fn grapheme_advance(grapheme: &str) -> f64 {
+    let c = match grapheme.chars().next() {
+        Some(c) => c,
+        None => return 0.0,
+    };
+    let advance = match c {
+        '\n' | '\r' | '\u{2028}' | '\u{2029}' => 0,
+        ' '..='~' => ASCII_ADVANCES[c as usize - 0x20],
+        _ => DEFAULT_ADVANCE,
+    };
+    advance as f64 / 1000.0
+}
@@ -19,8 +19,8 @@ use web_sys::{
 use piet::kurbo::{Affine, PathEl, Point, Rect, Shape};
 
 use piet::{
-    Color, Error, FixedGradient, GradientStop, ImageFormat, InterpolationMode, IntoBrush, LineCap,
-    LineJoin, RenderContext, StrokeStyle,
+    normalize_stops, Color, Error, FixedGradient, GradientStop, ImageFormat, InterpolationMode,
+    IntoBrush, LineCap, LineJoin, RenderContext, StrokeStyle,
 };
 
 pub use text::{WebFont, WebFontBuilder, WebTextLayout, WebTextLayoutBuilder};
@@ -135,7 +135,7 @@ impl<'a> RenderContext for WebRenderContext<'a> {
                 let (x0, y0) = (linear.start.x, linear.start.y);
                 let (x1, y1) = (linear.end.x, linear.end.y);
                 let mut lg = self.ctx.create_linear_gradient(x0, y0, x1, y1);
-                set_gradient_stops(&mut lg, &linear.stops);
+                set_gradient_stops(&mut lg, &normalize_stops(&linear.stops));
                 Ok(Brush::Gradient(lg))
             }
             FixedGradient::Radial(radial) => {
@@ -146,7 +146,7 @@ impl<'a> RenderContext for WebRenderContext<'a> {
                     .ctx
                     .create_radial_gradient(xc + xo, yc + yo, 0.0, xc, yc, r)
                     .wrap()?;
-                set_gradient_stops(&mut rg, &radial.stops);
+                set_gradient_stops(&mut rg, &normalize_stops(&radial.stops));
                 Ok(Brush::Gradient(rg))
             }
         }
@@ -445,38 +445,22 @@ impl WebRenderContext<'_> {
     fn set_stroke(&mut self, width: f64, style: Option<&StrokeStyle>) {
         self.ctx.set_line_width(width);
 
-        let line_join = style
-            .and_then(|style| style.line_join)
-            .unwrap_or(LineJoin::Miter);
-        self.ctx.set_line_join(convert_line_join(line_join));
-
-        let line_cap = style
-            .and_then(|style| style.line_cap)
-            .unwrap_or(LineCap::Butt);
-        self.ctx.set_line_cap(convert_line_cap(line_cap));
-
-        let miter_limit = style.and_then(|style| style.miter_limit).unwrap_or(10.0);
-        self.ctx.set_miter_limit(miter_limit);
-
-        let (dash_segs, dash_offset) = style
-            .and_then(|style| style.dash.as_ref())
-            .map(|dash| {
-                let len = dash.0.len() as u32;
-                let array = Float64Array::new_with_length(len);
-                for (i, elem) in dash.0.iter().enumerate() {
-                    Reflect::set(
-                        array.as_ref(),
-                        &JsValue::from(i as u32),
-                        &JsValue::from(*elem),
-                    )
-                    .unwrap();
-                }
-                (array, dash.1)
-            })
-            .unwrap_or((Float64Array::new_with_length(0), 0.0));
-
+        let style = style.map(StrokeStyle::resolve).unwrap_or_default();
+        self.ctx.set_line_join(convert_line_join(style.line_join));
+        self.ctx.set_line_cap(convert_line_cap(style.line_cap));
+        self.ctx.set_miter_limit(style.miter_limit);
+
+        let dash_segs = Float64Array::new_with_length(style.dashes.len() as u32);
+        for (i, elem) in style.dashes.iter().enumerate() {
+            Reflect::set(
+                dash_segs.as_ref(),
+                &JsValue::from(i as u32),
+                &JsValue::from(*elem),
+            )
+            .unwrap();
+        }
         self.ctx.set_line_dash(dash_segs.as_ref()).unwrap();
-        self.ctx.set_line_dash_offset(dash_offset);
+        self.ctx.set_line_dash_offset(style.dash_offset);
     }
 
     fn set_path(&mut self, shape: impl Shape) {
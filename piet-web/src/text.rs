@@ -223,6 +223,8 @@ impl TextLayout for WebTextLayout {
 
         let mut htp = hit_test_line_point(&self.ctx, line, &point);
         htp.metrics.text_position += lm.start_offset;
+        htp.metrics.is_trailing_whitespace =
+            htp.metrics.text_position > lm.end_offset - lm.trailing_whitespace;
 
         if !is_y_inside {
             htp.is_inside = false;
@@ -264,6 +266,8 @@ impl TextLayout for WebTextLayout {
         if let Some(h) = http.as_mut() {
             h.point.y = y;
             h.metrics.text_position += lm.start_offset;
+            h.metrics.is_trailing_whitespace =
+                h.metrics.text_position > lm.end_offset - lm.trailing_whitespace;
         };
         http
     }
@@ -361,6 +365,7 @@ fn hit_test_line_position(
             },
             metrics: HitTestMetrics {
                 text_position: text_len,
+                is_trailing_whitespace: false,
             },
         });
     }
@@ -377,7 +382,10 @@ fn hit_test_line_position(
 
         Some(HitTestTextPosition {
             point: Point { x: point_x, y: 0.0 },
-            metrics: HitTestMetrics { text_position },
+            metrics: HitTestMetrics {
+                text_position,
+                is_trailing_whitespace: false,
+            },
         })
     } else {
         // iterated to end boundary
@@ -388,6 +396,7 @@ fn hit_test_line_position(
             },
             metrics: HitTestMetrics {
                 text_position: text_len,
+                is_trailing_whitespace: false,
             },
         })
     }
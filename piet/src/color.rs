@@ -12,6 +12,18 @@ pub enum Color {
     Rgba32(u32),
 }
 
+/// The space in which colors are interpolated, see [`Color::interpolate`].
+///
+/// [`Color::interpolate`]: enum.Color.html#method.interpolate
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// Interpolate the gamma-encoded sRGB components, as CSS and SVG gradients do.
+    Srgb,
+    /// Interpolate the linear light components, which keeps the perceived brightness
+    /// of mid-points between saturated colors.
+    LinearSrgb,
+}
+
 impl Debug for Color {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         write!(f, "#{:08x}", self.as_rgba_u32())
@@ -193,6 +205,49 @@ impl Color {
         )
     }
 
+    /// Blend this color with `other`, where `t` is 0.0 for this color and 1.0 for `other`.
+    ///
+    /// The color components are interpolated in the given space, and alpha is always
+    /// interpolated linearly. `t` is clamped to the range 0.0 to 1.0.
+    ///
+    /// ```
+    /// use piet::{Color, ColorSpace};
+    ///
+    /// let mid = Color::BLACK.interpolate(&Color::WHITE, 0.5, ColorSpace::Srgb);
+    /// assert_eq!(mid.as_rgba_u32(), 0x808080ff);
+    ///
+    /// let mid = Color::BLACK.interpolate(&Color::WHITE, 0.5, ColorSpace::LinearSrgb);
+    /// assert_eq!(mid.as_rgba_u32(), 0xbcbcbcff);
+    /// ```
+    pub fn interpolate(&self, other: &Color, t: f64, space: ColorSpace) -> Color {
+        fn to_linear(u: f64) -> f64 {
+            if u <= 0.04045 {
+                u / 12.92
+            } else {
+                ((u + 0.055) / 1.055).powf(2.4)
+            }
+        }
+        fn from_linear(u: f64) -> f64 {
+            if u <= 0.003_130_8 {
+                12.92 * u
+            } else {
+                1.055 * u.powf(1. / 2.4) - 0.055
+            }
+        }
+        let t = t.clamp(0.0, 1.0);
+        let (r0, g0, b0, a0) = Color::as_rgba(self);
+        let (r1, g1, b1, a1) = Color::as_rgba(other);
+        let lerp = |x0: f64, x1: f64| x0 + (x1 - x0) * t;
+        let a = lerp(a0, a1);
+        match space {
+            ColorSpace::Srgb => Color::rgba(lerp(r0, r1), lerp(g0, g1), lerp(b0, b1), a),
+            ColorSpace::LinearSrgb => {
+                let mix = |x0, x1| from_linear(lerp(to_linear(x0), to_linear(x1)));
+                Color::rgba(mix(r0, r1), mix(g0, g1), mix(b0, b1), a)
+            }
+        }
+    }
+
     /// Opaque white.
     pub const WHITE: Color = Color::rgb8(0xff, 0xff, 0xff);
 
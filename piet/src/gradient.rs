@@ -30,7 +30,7 @@ use kurbo::{Point, Rect, Size, Vec2};
 
 use crate::{IntoBrush, RenderContext};
 
-use crate::Color;
+use crate::{Color, ColorSpace};
 
 /// Specification of a linear gradient.
 ///
@@ -49,6 +49,8 @@ pub struct FixedLinearGradient {
     ///
     /// There must be at least two for the gradient to be valid.
     pub stops: Vec<GradientStop>,
+    /// How the gradient is extended before the start and past the end.
+    pub extend: GradientExtend,
 }
 
 /// Specification of a radial gradient in image-space.
@@ -70,6 +72,8 @@ pub struct FixedRadialGradient {
     pub radius: f64,
     /// The stops (see similar field in [`LinearGradient`](struct.LinearGradient.html)).
     pub stops: Vec<GradientStop>,
+    /// How the gradient is extended past the circle.
+    pub extend: GradientExtend,
 }
 
 /// Any fixed gradient.
@@ -89,6 +93,19 @@ pub enum FixedGradient {
     Radial(FixedRadialGradient),
 }
 
+/// How a gradient is painted outside of the range between its first and last stop.
+///
+/// Not all backends support every mode; those that don't fall back to `Pad`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GradientExtend {
+    /// The colors of the first and last stops are extended indefinitely.
+    Pad,
+    /// The gradient is repeated.
+    Repeat,
+    /// The gradient is repeated, reversing direction each time.
+    Reflect,
+}
+
 /// Specification of a gradient stop.
 #[derive(Debug, Clone)]
 pub struct GradientStop {
@@ -98,6 +115,79 @@ pub struct GradientStop {
     pub color: Color,
 }
 
+/// Put gradient stops into the form that backends expect.
+///
+/// The stops are sorted by position, and clamped to the range 0.0 to 1.0. A stop outside
+/// of that range is replaced by the color the gradient has at the end of the range, so
+/// the visible part of the gradient is unchanged. A single stop is duplicated, so that
+/// there are at least two unless `stops` is empty. Stops at infinite or NaN positions
+/// are ignored.
+///
+/// ```
+/// use piet::{normalize_stops, Color, GradientStop};
+///
+/// let stops = normalize_stops(&[
+///     GradientStop { pos: 2.0, color: Color::WHITE },
+///     GradientStop { pos: 0.0, color: Color::BLACK },
+/// ]);
+/// assert_eq!(stops.len(), 2);
+/// assert_eq!((stops[0].pos, stops[0].color.as_rgba_u32()), (0.0, 0x000000ff));
+/// assert_eq!((stops[1].pos, stops[1].color.as_rgba_u32()), (1.0, 0x808080ff));
+///
+/// let stops = normalize_stops(&[
+///     GradientStop { pos: f32::NEG_INFINITY, color: Color::WHITE },
+///     GradientStop { pos: 0.5, color: Color::BLACK },
+/// ]);
+/// assert_eq!(stops.len(), 2);
+/// for stop in &stops {
+///     assert_eq!((stop.pos, stop.color.as_rgba_u32()), (0.5, 0x000000ff));
+/// }
+/// ```
+pub fn normalize_stops(stops: &[GradientStop]) -> Vec<GradientStop> {
+    let mut sorted = stops
+        .iter()
+        .filter(|stop| stop.pos.is_finite())
+        .cloned()
+        .collect::<Vec<_>>();
+    sorted.sort_by(|a, b| a.pos.partial_cmp(&b.pos).unwrap());
+    let mut out = Vec::with_capacity(sorted.len() + 2);
+    if matches!(sorted.first(), Some(stop) if stop.pos < 0.0) {
+        out.push(GradientStop {
+            pos: 0.0,
+            color: color_at(&sorted, 0.0),
+        });
+    }
+    out.extend(
+        sorted
+            .iter()
+            .filter(|stop| stop.pos >= 0.0 && stop.pos <= 1.0)
+            .cloned(),
+    );
+    if matches!(sorted.last(), Some(stop) if stop.pos > 1.0) {
+        out.push(GradientStop {
+            pos: 1.0,
+            color: color_at(&sorted, 1.0),
+        });
+    }
+    if out.len() == 1 {
+        out.push(out[0].clone());
+    }
+    out
+}
+
+/// The color of the gradient with the given sorted, non-empty stops at `pos`.
+fn color_at(stops: &[GradientStop], pos: f32) -> Color {
+    match stops.iter().position(|stop| stop.pos >= pos) {
+        Some(0) => stops[0].color.clone(),
+        Some(i) => {
+            let (a, b) = (&stops[i - 1], &stops[i]);
+            let t = (pos - a.pos) / (b.pos - a.pos);
+            a.color.interpolate(&b.color, t.into(), ColorSpace::Srgb)
+        }
+        None => stops[stops.len() - 1].color.clone(),
+    }
+}
+
 /// A flexible, ergonomic way to describe gradient stops.
 pub trait GradientStops {
     fn to_vec(self) -> Vec<GradientStop>;
@@ -118,6 +208,7 @@ pub struct LinearGradient {
     start: UnitPoint,
     end: UnitPoint,
     stops: Vec<GradientStop>,
+    extend: GradientExtend,
 }
 
 /// A description of a radial gradient in the unit rect, which can be resolved
@@ -154,6 +245,7 @@ pub struct RadialGradient {
     radius: f64,
     stops: Vec<GradientStop>,
     scale_mode: ScaleMode,
+    extend: GradientExtend,
 }
 
 /// Mappings from the unit square into a non-square rectangle.
@@ -295,9 +387,18 @@ impl LinearGradient {
             start,
             end,
             stops: stops.to_vec(),
+            extend: GradientExtend::Pad,
         }
     }
 
+    /// A builder-style method for changing the [`GradientExtend`] mode of the gradient.
+    ///
+    /// [`GradientExtend`]: enum.GradientExtend.html
+    pub fn with_extend(mut self, extend: GradientExtend) -> Self {
+        self.extend = extend;
+        self
+    }
+
     // maybe these should be public API? that was my original intention but I'm not
     // sure there's a clear use, so keeping them private for now.
     /// Generate a [`FixedLinearGradient`] by mapping points in the unit square
@@ -309,6 +410,7 @@ impl LinearGradient {
             start: self.start.resolve(rect),
             end: self.end.resolve(rect),
             stops: self.stops.clone(),
+            extend: self.extend,
         }
     }
 }
@@ -330,6 +432,7 @@ impl RadialGradient {
             radius,
             stops: stops.to_vec(),
             scale_mode: ScaleMode::Fill,
+            extend: GradientExtend::Pad,
         }
     }
 
@@ -363,6 +466,14 @@ impl RadialGradient {
         self
     }
 
+    /// A builder-style method for changing the [`GradientExtend`] mode of the gradient.
+    ///
+    /// [`GradientExtend`]: enum.GradientExtend.html
+    pub fn with_extend(mut self, extend: GradientExtend) -> Self {
+        self.extend = extend;
+        self
+    }
+
     /// Generate a [`FixedRadialGradient`] by mapping points in the unit square
     /// onto points in `rect`.
     ///
@@ -383,6 +494,7 @@ impl RadialGradient {
             origin_offset,
             radius,
             stops: self.stops.clone(),
+            extend: self.extend,
         }
     }
 }
@@ -12,9 +12,25 @@ use crate::{
 
 /// A render context that doesn't render.
 ///
-/// This is useful largely for doc tests, but is made public in case
-/// it might come in handy.
-#[doc(hidden)]
+/// This is useful for doc tests, and for testing drawing code without a real
+/// backend. All drawing operations succeed and do nothing. Text is measured
+/// deterministically: every character is half an em wide, and the text is laid
+/// out on a single line.
+///
+/// ```
+/// use piet::kurbo::Rect;
+/// use piet::{Color, FontBuilder, NullRenderContext, RenderContext, Text, TextLayout,
+///     TextLayoutBuilder};
+///
+/// let mut ctx = NullRenderContext::new();
+/// ctx.fill(Rect::new(0.0, 0.0, 10.0, 10.0), &Color::BLACK);
+/// ctx.stroke(Rect::new(0.0, 0.0, 10.0, 10.0), &Color::BLACK, 1.0);
+/// let font = ctx.text().new_font_by_name("sans-serif", 10.0).build().unwrap();
+/// let layout = ctx.text().new_text_layout(&font, "piet", None).build().unwrap();
+/// assert_eq!(layout.width(), 20.0);
+/// ctx.draw_text(&layout, (0.0, 10.0), &Color::BLACK);
+/// assert!(ctx.finish().is_ok());
+/// ```
 pub struct NullRenderContext(NullText);
 
 #[derive(Clone)]
@@ -27,15 +43,18 @@ pub struct NullImage;
 pub struct NullText;
 
 #[doc(hidden)]
-pub struct NullFont;
+pub struct NullFont(f64);
 #[doc(hidden)]
-pub struct NullFontBuilder;
+pub struct NullFontBuilder(f64);
 
 #[doc(hidden)]
 #[derive(Clone)]
-pub struct NullTextLayout;
+pub struct NullTextLayout {
+    text: String,
+    size: f64,
+}
 #[doc(hidden)]
-pub struct NullTextLayoutBuilder;
+pub struct NullTextLayoutBuilder(NullTextLayout);
 
 impl NullRenderContext {
     #[allow(clippy::new_without_default)]
@@ -142,17 +161,20 @@ impl Text for NullText {
     type TextLayout = NullTextLayout;
     type TextLayoutBuilder = NullTextLayoutBuilder;
 
-    fn new_font_by_name(&mut self, _name: &str, _size: f64) -> Self::FontBuilder {
-        NullFontBuilder
+    fn new_font_by_name(&mut self, _name: &str, size: f64) -> Self::FontBuilder {
+        NullFontBuilder(size)
     }
 
     fn new_text_layout(
         &mut self,
-        _font: &Self::Font,
-        _text: &str,
+        font: &Self::Font,
+        text: &str,
         _width: impl Into<Option<f64>>,
     ) -> Self::TextLayoutBuilder {
-        NullTextLayoutBuilder
+        NullTextLayoutBuilder(NullTextLayout {
+            text: text.to_owned(),
+            size: font.0,
+        })
     }
 }
 
@@ -162,7 +184,7 @@ impl FontBuilder for NullFontBuilder {
     type Out = NullFont;
 
     fn build(self) -> Result<Self::Out, Error> {
-        Ok(NullFont)
+        Ok(NullFont(self.0))
     }
 }
 
@@ -170,29 +192,44 @@ impl TextLayoutBuilder for NullTextLayoutBuilder {
     type Out = NullTextLayout;
 
     fn build(self) -> Result<Self::Out, Error> {
-        Ok(NullTextLayout)
+        Ok(self.0)
     }
 }
 
 impl TextLayout for NullTextLayout {
     fn width(&self) -> f64 {
-        42.0
+        self.text.chars().count() as f64 * self.size / 2.0
     }
 
     fn update_width(&mut self, _new_width: impl Into<Option<f64>>) -> Result<(), Error> {
         Ok(())
     }
 
-    fn line_text(&self, _line_number: usize) -> Option<&str> {
-        None
+    fn line_text(&self, line_number: usize) -> Option<&str> {
+        if line_number == 0 {
+            Some(&self.text)
+        } else {
+            None
+        }
     }
 
-    fn line_metric(&self, _line_number: usize) -> Option<LineMetric> {
-        None
+    fn line_metric(&self, line_number: usize) -> Option<LineMetric> {
+        if line_number == 0 {
+            Some(LineMetric {
+                start_offset: 0,
+                end_offset: self.text.len(),
+                trailing_whitespace: self.text.len() - self.text.trim_end().len(),
+                baseline: self.size * 0.8,
+                height: self.size,
+                cumulative_height: self.size,
+            })
+        } else {
+            None
+        }
     }
 
     fn line_count(&self) -> usize {
-        0
+        1
     }
 
     fn hit_test_point(&self, _point: Point) -> HitTestPoint {
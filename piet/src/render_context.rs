@@ -18,6 +18,31 @@ pub enum InterpolationMode {
     Bilinear,
 }
 
+/// A blend mode, describing how drawing is composited with what is below it.
+///
+/// These are the blend modes of the [Compositing and Blending] spec.
+///
+/// [Compositing and Blending]: https://www.w3.org/TR/compositing-1/#blending
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlendMode {
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+    Hue,
+    Saturation,
+    Color,
+    Luminosity,
+}
+
 /// The pixel format for bitmap images.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ImageFormat {
@@ -27,6 +52,8 @@ pub enum ImageFormat {
     RgbaSeparate,
     /// 4 bytes per pixel, in RGBA order, with premultiplied alpha.
     RgbaPremul,
+    /// 1 byte per pixel, the luminance.
+    Grayscale,
     /// More formats may be added later.
     #[doc(hidden)]
     _NonExhaustive,
@@ -37,6 +64,7 @@ impl ImageFormat {
         match self {
             ImageFormat::Rgb => 3,
             ImageFormat::RgbaPremul | ImageFormat::RgbaSeparate => 4,
+            ImageFormat::Grayscale => 1,
             _ => panic!(),
         }
     }
@@ -67,3 +67,66 @@ impl StrokeStyle {
         self.miter_limit = Some(miter_limit);
     }
 }
+
+/// A [`StrokeStyle`] with the defaults filled in and the dash pattern normalized, in
+/// the form that backends consume.
+///
+/// [`StrokeStyle`]: struct.StrokeStyle.html
+#[derive(Clone, PartialEq, Debug)]
+pub struct ResolvedStrokeStyle {
+    pub line_join: LineJoin,
+    pub line_cap: LineCap,
+    /// The lengths of the dash pattern, which has an even number of them, or is empty
+    /// for a solid line.
+    pub dashes: Vec<f64>,
+    /// The offset into the dash pattern, which is 0.0 for a solid line.
+    pub dash_offset: f64,
+    pub miter_limit: f64,
+}
+
+impl StrokeStyle {
+    /// Fill in the defaults, and normalize the dash pattern.
+    ///
+    /// A dash pattern that is empty, has negative lengths, or only lengths of zero draws a
+    /// solid line. A pattern with an odd number of lengths is repeated to make it even.
+    /// The miter limit defaults to 10.0, and is at least 1.0.
+    ///
+    /// ```
+    /// use piet::StrokeStyle;
+    ///
+    /// let mut style = StrokeStyle::new();
+    /// style.set_dash(vec![3.0, 1.0, 2.0], 1.0);
+    /// let resolved = style.resolve();
+    /// assert_eq!(resolved.dashes, [3.0, 1.0, 2.0, 3.0, 1.0, 2.0]);
+    /// assert_eq!(resolved.dash_offset, 1.0);
+    /// assert_eq!(resolved.miter_limit, 10.0);
+    ///
+    /// style.set_dash(vec![], 1.0);
+    /// assert!(style.resolve().dashes.is_empty());
+    /// assert_eq!(style.resolve().dash_offset, 0.0);
+    /// ```
+    pub fn resolve(&self) -> ResolvedStrokeStyle {
+        let (dashes, dash_offset) = match self.dash {
+            Some((ref dashes, offset))
+                if dashes.iter().all(|&d| d >= 0.0) && dashes.iter().any(|&d| d > 0.0) =>
+            {
+                let copies = if dashes.len() % 2 == 1 { 2 } else { 1 };
+                (dashes.repeat(copies), offset)
+            }
+            _ => (Vec::new(), 0.0),
+        };
+        ResolvedStrokeStyle {
+            line_join: self.line_join.unwrap_or(LineJoin::Miter),
+            line_cap: self.line_cap.unwrap_or(LineCap::Butt),
+            dashes,
+            dash_offset,
+            miter_limit: self.miter_limit.unwrap_or(10.0).max(1.0),
+        }
+    }
+}
+
+impl Default for ResolvedStrokeStyle {
+    fn default() -> Self {
+        StrokeStyle::new().resolve()
+    }
+}
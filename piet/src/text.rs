@@ -190,6 +190,9 @@ pub struct HitTestTextPosition {
 /// and [`hit_test_point`](../piet/trait.TextLayout.html#tymethod.hit_test_point).
 pub struct HitTestMetrics {
     pub text_position: usize,
+    /// Whether the text position is inside the trailing whitespace of its line, that is,
+    /// after the start of the whitespace.
+    pub is_trailing_whitespace: bool,
     // TODO:
     // consider adding other metrics as needed, such as those provided in
     // [DWRITE_HIT_TEST_METRICS](https://docs.microsoft.com/en-us/windows/win32/api/dwrite/ns-dwrite-dwrite_hit_test_metrics).